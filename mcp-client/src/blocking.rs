@@ -0,0 +1,45 @@
+// Synchronous wrapper for callers without a tokio runtime (scripts, build tools).
+// It owns a current-thread runtime and blocks on each call, so it must not be
+// used from inside an async context - tokio panics on a nested block_on. Async
+// code should use McpClient directly.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use tokio::runtime::Runtime;
+
+use crate::McpClient;
+use crate::protocol::*;
+
+pub struct BlockingMcpClient {
+    runtime: Runtime,
+    client: McpClient,
+}
+
+impl BlockingMcpClient {
+    pub fn connect(command: &str, args: &[String]) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to build runtime for blocking client")?;
+        let client = runtime.block_on(McpClient::connect(command, args))?;
+
+        Ok(Self { runtime, client })
+    }
+
+    pub fn initialize(&mut self, client_name: &str, client_version: &str) -> Result<InitializeResult> {
+        self.runtime
+            .block_on(self.client.initialize(client_name, client_version))
+    }
+
+    pub fn list_tools(&mut self) -> Result<Vec<Tool>> {
+        self.runtime.block_on(self.client.list_tools())
+    }
+
+    pub fn call_tool(&mut self, name: &str, arguments: Value) -> Result<Value> {
+        self.runtime.block_on(self.client.call_tool(name, arguments))
+    }
+
+    pub fn call_tool_full(&mut self, name: &str, arguments: Value) -> Result<CallToolResult> {
+        self.runtime.block_on(self.client.call_tool_full(name, arguments))
+    }
+}
@@ -1,15 +1,23 @@
 use anyhow::{Context, Result};
 use serde_json::{json, Value};
+use std::time::Duration;
 use tokio::process::{Child, Command};
 
+pub mod blocking;
 pub mod protocol;
+pub mod reconnecting;
 pub mod transport;
 
 use protocol::*;
 
 pub struct McpClient {
     transport: transport::StdioTransport,
+    // Id of the last request sent; never reset, so ids stay unique across reconnects
     request_id: u64,
+    // Upper bound on waiting for each response - None waits forever
+    timeout: Option<Duration>,
+    // Set after a timeout: a late response would be read as the answer to the next request
+    desynced: bool,
 }
 
 impl McpClient {
@@ -26,9 +34,41 @@ impl McpClient {
         Ok(Self {
             transport: transport::StdioTransport::new(stdin, stdout, process),
             request_id: 0,
+            timeout: None,
+            desynced: false,
         })
     }
 
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    // Carry ids over from a previous connection so they keep increasing
+    pub fn with_request_ids_after(mut self, last_id: u64) -> Self {
+        self.request_id = self.request_id.max(last_id);
+        self
+    }
+
+    // Id of the most recent request, 0 before the first one - for log correlation
+    pub fn current_request_id(&self) -> u64 {
+        self.request_id
+    }
+
+    // False after the server exits or a request times out - either way, reconnect
+    pub fn is_connected(&mut self) -> bool {
+        !self.desynced && self.transport.is_alive()
+    }
+
+    pub fn server_pid(&self) -> Option<u32> {
+        self.transport.server_pid()
+    }
+
+    // Stop the server process; the client is unusable afterwards
+    pub async fn shutdown(&mut self) {
+        self.transport.shutdown().await
+    }
+
     pub async fn connect(command: &str, args: &[String]) -> Result<Self> {
         let mut cmd = Command::new(command);
         for arg in args {
@@ -38,6 +78,8 @@ impl McpClient {
         cmd.stdin(std::process::Stdio::piped());
         cmd.stdout(std::process::Stdio::piped());
         cmd.stderr(std::process::Stdio::piped());
+        // A dropped client takes its server with it
+        cmd.kill_on_drop(true);
 
         let process = cmd.spawn().context("Failed to spawn MCP server process")?;
 
@@ -48,11 +90,30 @@ impl McpClient {
         &mut self,
         client_name: &str,
         client_version: &str,
+    ) -> Result<InitializeResult> {
+        self.initialize_session(client_name, client_version, false).await
+    }
+
+    // Limits this session to tools the server marks read_only
+    pub async fn initialize_read_only(
+        &mut self,
+        client_name: &str,
+        client_version: &str,
+    ) -> Result<InitializeResult> {
+        self.initialize_session(client_name, client_version, true).await
+    }
+
+    async fn initialize_session(
+        &mut self,
+        client_name: &str,
+        client_version: &str,
+        read_only: bool,
     ) -> Result<InitializeResult> {
         let params = InitializeParams {
             protocol_version: "2024-11-05".to_string(),
             capabilities: ClientCapabilities {
                 tools: Some(ToolsCapability {}),
+                read_only,
             },
             client_info: ClientInfo {
                 name: client_name.to_string(),
@@ -70,6 +131,11 @@ impl McpClient {
         serde_json::from_value(response).context("Failed to parse initialize response")
     }
 
+    pub async fn ping(&mut self) -> Result<PingResult> {
+        let response = self.request("ping", None).await?;
+        serde_json::from_value(response).context("Failed to parse ping response")
+    }
+
     pub async fn list_tools(&mut self) -> Result<Vec<Tool>> {
         let response = self.request("tools/list", None).await?;
         let result: ListToolsResult =
@@ -77,7 +143,14 @@ impl McpClient {
         Ok(result.tools)
     }
 
+    // The tool's JSON output; use call_tool_full for images and embedded resources
     pub async fn call_tool(&mut self, name: &str, arguments: Value) -> Result<Value> {
+        self.call_tool_full(name, arguments).await?.json()
+    }
+
+    // Every content block is kept - json() gives the usual JSON result,
+    // images() and resources() anything else the tool returned
+    pub async fn call_tool_full(&mut self, name: &str, arguments: Value) -> Result<CallToolResult> {
         let params = CallToolParams {
             name: name.to_string(),
             arguments,
@@ -89,21 +162,54 @@ impl McpClient {
         let result: CallToolResult =
             serde_json::from_value(response).context("Failed to parse tool call result")?;
 
-        if result.is_error.unwrap_or(false) {
-            if let Some(ContentBlock::Text { text }) = result.content.first() {
-                anyhow::bail!("Tool error: {}", text);
-            }
-        }
+        Self::tool_result(result)
+    }
+
+    // Several calls in one round trip (tools/call_batch). Results are in call order,
+    // each Ok or Err exactly as call_tool_full would have returned it
+    pub async fn call_tools_batch(&mut self, calls: Vec<(String, Value)>) -> Result<Vec<Result<CallToolResult>>> {
+        let params = CallToolBatchParams {
+            calls: calls
+                .into_iter()
+                .map(|(name, arguments)| CallToolParams { name, arguments })
+                .collect(),
+            max_concurrency: None,
+        };
 
-        // Extract the text content
-        if let Some(ContentBlock::Text { text }) = result.content.first() {
-            serde_json::from_str(text).context("Failed to parse tool result")
-        } else {
-            Ok(Value::Null)
+        let response = self
+            .request("tools/call_batch", Some(serde_json::to_value(params)?))
+            .await?;
+        let batch: CallToolBatchResult =
+            serde_json::from_value(response).context("Failed to parse tool call batch result")?;
+
+        Ok(batch
+            .results
+            .into_iter()
+            .map(|entry| match entry {
+                BatchCallResult::Failed { error } => {
+                    Err(anyhow::anyhow!("RPC error {}: {}", error.code, error.message))
+                }
+                BatchCallResult::Completed(result) => Self::tool_result(result),
+            })
+            .collect())
+    }
+
+    // A result flagged isError becomes an error carrying its message
+    fn tool_result(result: CallToolResult) -> Result<CallToolResult> {
+        if result.is_error.unwrap_or(false)
+            && let Some(ContentBlock::Text { text }) = result.content.first()
+        {
+            anyhow::bail!("Tool error: {}", text);
         }
+
+        Ok(result)
     }
 
     async fn request(&mut self, method: &str, params: Option<Value>) -> Result<Value> {
+        if self.desynced {
+            anyhow::bail!("MCP connection is unusable after a timed-out request; reconnect");
+        }
+
         self.request_id += 1;
         let request = JsonRpcRequest {
             jsonrpc: "2.0".to_string(),
@@ -112,7 +218,17 @@ impl McpClient {
             params,
         };
 
-        self.transport.send_request(&request).await
+        let Some(timeout) = self.timeout else {
+            return self.transport.send_request(&request).await;
+        };
+
+        match tokio::time::timeout(timeout, self.transport.send_request(&request)).await {
+            Ok(result) => result,
+            Err(_) => {
+                self.desynced = true;
+                Err(anyhow::anyhow!("MCP request '{}' timed out", method))
+            }
+        }
     }
 
     async fn notify(&mut self, method: &str, params: Option<Value>) -> Result<()> {
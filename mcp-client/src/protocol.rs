@@ -55,6 +55,9 @@ pub struct InitializeParams {
 pub struct ClientCapabilities {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<ToolsCapability>,
+    // Client asks for a session limited to tools marked read_only
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -94,6 +97,15 @@ pub struct Tool {
     pub description: String,
     #[serde(rename = "inputSchema")]
     pub input_schema: Value,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+// Liveness check - answered without touching tools or requiring initialize
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PingResult {
+    pub ok: bool,
+    pub uptime_ms: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -107,11 +119,61 @@ pub struct CallToolParams {
     pub arguments: Value,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CallToolBatchParams {
+    pub calls: Vec<CallToolParams>,
+    #[serde(rename = "maxConcurrency", default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CallToolBatchResult {
+    pub results: Vec<BatchCallResult>,
+}
+
+// One batch entry - an RPC error for that call, or its tools/call result
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BatchCallResult {
+    Failed { error: JsonRpcError },
+    Completed(CallToolResult),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CallToolResult {
     pub content: Vec<ContentBlock>,
     #[serde(rename = "isError", skip_serializing_if = "Option::is_none")]
     pub is_error: Option<bool>,
+    // Machine-readable detail about a failure, e.g. {"reason": "timeout", "signal": "SIGKILL"}
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+impl CallToolResult {
+    // The tool's JSON output, carried in the first text block
+    pub fn json(&self) -> anyhow::Result<Value> {
+        match self.content.first() {
+            Some(ContentBlock::Text { text }) => {
+                serde_json::from_str(text).map_err(|e| anyhow::anyhow!("Failed to parse tool result: {}", e))
+            }
+            _ => Ok(Value::Null),
+        }
+    }
+
+    // (base64 data, mime type) for each image block
+    pub fn images(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.content.iter().filter_map(|block| match block {
+            ContentBlock::Image { data, mime_type } => Some((data.as_str(), mime_type.as_str())),
+            _ => None,
+        })
+    }
+
+    pub fn resources(&self) -> impl Iterator<Item = &EmbeddedResource> {
+        self.content.iter().filter_map(|block| match block {
+            ContentBlock::Resource { resource } => Some(resource),
+            _ => None,
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -119,4 +181,25 @@ pub struct CallToolResult {
 pub enum ContentBlock {
     #[serde(rename = "text")]
     Text { text: String },
+    #[serde(rename = "image")]
+    Image {
+        // Base64-encoded image bytes
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    #[serde(rename = "resource")]
+    Resource { resource: EmbeddedResource },
+}
+
+// Embedded resource content - text for textual formats, base64 blob otherwise
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddedResource {
+    pub uri: String,
+    #[serde(rename = "mimeType", default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
 }
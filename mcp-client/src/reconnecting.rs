@@ -0,0 +1,130 @@
+// Client for long-running hosts that outlive their server process. It keeps the
+// command line and client info, and when a request fails because the connection
+// is gone it respawns the server, initializes again, and retries that request
+// once. The new server loads its tools from YAML afresh.
+//
+// A retried tools/call may run the tool twice if the server died after starting
+// it - only use this with tools that tolerate that.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::McpClient;
+use crate::protocol::*;
+
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+pub struct ReconnectingMcpClient {
+    command: String,
+    args: Vec<String>,
+    client_name: String,
+    client_version: String,
+    timeout: Option<Duration>,
+    retry_delay: Duration,
+    client: McpClient,
+}
+
+impl ReconnectingMcpClient {
+    pub async fn connect(
+        command: &str,
+        args: &[String],
+        client_name: &str,
+        client_version: &str,
+    ) -> Result<Self> {
+        let mut client = McpClient::connect(command, args).await?;
+        client.initialize(client_name, client_version).await?;
+
+        Ok(Self {
+            command: command.to_string(),
+            args: args.to_vec(),
+            client_name: client_name.to_string(),
+            client_version: client_version.to_string(),
+            timeout: None,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            client,
+        })
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = self.client.with_timeout(timeout);
+        self.timeout = Some(timeout);
+        self
+    }
+
+    // Pause before respawning, so a server that crashes on startup isn't hammered
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    pub fn server_pid(&self) -> Option<u32> {
+        self.client.server_pid()
+    }
+
+    pub fn current_request_id(&self) -> u64 {
+        self.client.current_request_id()
+    }
+
+    pub async fn ping(&mut self) -> Result<PingResult> {
+        match self.client.ping().await {
+            Err(e) => {
+                self.recover(e).await?;
+                self.client.ping().await
+            }
+            result => result,
+        }
+    }
+
+    pub async fn list_tools(&mut self) -> Result<Vec<Tool>> {
+        match self.client.list_tools().await {
+            Err(e) => {
+                self.recover(e).await?;
+                self.client.list_tools().await
+            }
+            result => result,
+        }
+    }
+
+    pub async fn call_tool(&mut self, name: &str, arguments: Value) -> Result<Value> {
+        self.call_tool_full(name, arguments).await?.json()
+    }
+
+    pub async fn call_tool_full(&mut self, name: &str, arguments: Value) -> Result<CallToolResult> {
+        match self.client.call_tool_full(name, arguments.clone()).await {
+            Err(e) => {
+                self.recover(e).await?;
+                self.client.call_tool_full(name, arguments).await
+            }
+            result => result,
+        }
+    }
+
+    // Errors from a live connection (tool errors, RPC errors) pass through untouched
+    async fn recover(&mut self, error: anyhow::Error) -> Result<()> {
+        if self.client.is_connected() {
+            return Err(error);
+        }
+
+        warn!("MCP connection lost ({}); restarting '{}'", error, self.command);
+        tokio::time::sleep(self.retry_delay).await;
+
+        // Ids carry on from the dead connection so they stay unique
+        let mut client = McpClient::connect(&self.command, &self.args)
+            .await?
+            .with_request_ids_after(self.client.current_request_id());
+        if let Some(timeout) = self.timeout {
+            client = client.with_timeout(timeout);
+        }
+        client
+            .initialize(&self.client_name, &self.client_version)
+            .await
+            .with_context(|| format!("Failed to reinitialize after: {}", error))?;
+
+        // After a timeout the old server may still be running - stop it before replacing it
+        self.client.shutdown().await;
+        self.client = client;
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, ChildStdin, ChildStdout};
 use tracing::debug;
 
@@ -8,7 +9,7 @@ use crate::protocol::*;
 pub struct StdioTransport {
     stdin: ChildStdin,
     stdout: BufReader<ChildStdout>,
-    _process: Child,
+    process: Child,
 }
 
 impl StdioTransport {
@@ -16,7 +17,7 @@ impl StdioTransport {
         Self {
             stdin,
             stdout: BufReader::new(stdout),
-            _process: process,
+            process,
         }
     }
 
@@ -25,13 +26,13 @@ impl StdioTransport {
         let request_str = serde_json::to_string(request)?;
         debug!("Sending request: {}", request_str);
 
-        self.stdin.write_all(request_str.as_bytes()).await?;
-        self.stdin.write_all(b"\n").await?;
-        self.stdin.flush().await?;
+        self.write_line(&request_str).await?;
 
-        // Read response
+        // Read response - EOF means the server is gone, not that it sent garbage
         let mut response_line = String::new();
-        self.stdout.read_line(&mut response_line).await?;
+        if self.stdout.read_line(&mut response_line).await? == 0 {
+            return Err(self.exit_error().await);
+        }
 
         debug!("Received response: {}", response_line);
 
@@ -49,10 +50,57 @@ impl StdioTransport {
         let notification_str = serde_json::to_string(notification)?;
         debug!("Sending notification: {}", notification_str);
 
-        self.stdin.write_all(notification_str.as_bytes()).await?;
-        self.stdin.write_all(b"\n").await?;
-        self.stdin.flush().await?;
+        self.write_line(&notification_str).await
+    }
 
-        Ok(())
+    // False once the server process has exited
+    pub fn is_alive(&mut self) -> bool {
+        matches!(self.process.try_wait(), Ok(None))
+    }
+
+    pub fn server_pid(&self) -> Option<u32> {
+        self.process.id()
+    }
+
+    // Kill and reap the server - used when replacing a connection that may still be running
+    pub async fn shutdown(&mut self) {
+        let _ = self.process.start_kill();
+        let _ = self.process.wait().await;
+    }
+
+    async fn write_line(&mut self, line: &str) -> Result<()> {
+        let written = async {
+            self.stdin.write_all(line.as_bytes()).await?;
+            self.stdin.write_all(b"\n").await?;
+            self.stdin.flush().await
+        }
+        .await;
+
+        match written {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Err(self.exit_error().await),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Reap the dead server so the error says why it went away
+    async fn exit_error(&mut self) -> anyhow::Error {
+        let status = match tokio::time::timeout(Duration::from_secs(1), self.process.wait()).await {
+            Ok(Ok(status)) => status.to_string(),
+            _ => "status unknown".to_string(),
+        };
+
+        let mut stderr = String::new();
+        if let Some(mut pipe) = self.process.stderr.take() {
+            let _ = tokio::time::timeout(Duration::from_secs(1), pipe.read_to_string(&mut stderr))
+                .await;
+        }
+
+        let stderr = stderr.trim();
+        if stderr.is_empty() {
+            anyhow::anyhow!("MCP server process exited ({})", status)
+        } else {
+            anyhow::anyhow!("MCP server process exited ({}); stderr: {}", status, stderr)
+        }
     }
 }
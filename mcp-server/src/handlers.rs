@@ -2,31 +2,51 @@
 // No request reaches tool execution without explicit validation.
 
 use anyhow::Result;
-use serde_json::Value;
+use futures_util::stream::{self, StreamExt};
+use serde_json::{json, Value};
 use std::collections::HashMap;
-use tracing::{debug, error, info};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use tracing::{debug, error, info, warn};
 
 use crate::protocol::*;
-use crate::tools::ToolManager;
+use crate::tools::{ToolError, ToolManager, CONTENT_KEY, RESOURCE_KEY};
+
+// Most calls one tools/call_batch may carry, and most it runs at once
+const MAX_BATCH_CALLS: usize = 32;
+const MAX_BATCH_CONCURRENCY: usize = 8;
 
 pub struct RequestHandler {
     tool_manager: ToolManager,
     injected_values: HashMap<String, String>,
+    started: Instant,
+    // One handshake per session - a second initialize is rejected
+    initialized: AtomicBool,
+    // Client opted into read-only at initialize; it can only tighten, never loosen
+    read_only: AtomicBool,
 }
 
 impl RequestHandler {
     pub fn new(tool_manager: ToolManager, injected_values: HashMap<String, String>) -> Self {
-        Self { tool_manager, injected_values }
+        Self {
+            tool_manager,
+            injected_values,
+            started: Instant::now(),
+            initialized: AtomicBool::new(false),
+            read_only: AtomicBool::new(false),
+        }
     }
 
-    // Request dispatch - only these three methods exist, nothing else
+    // Request dispatch - only these methods exist, nothing else
     pub async fn handle_request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
         debug!("Handling request: {} (id: {})", request.method, request.id);
 
         let result = match request.method.as_str() {
             "initialize" => self.handle_initialize(request.params).await,
-            "tools/list" => self.handle_tools_list().await,
+            "tools/list" => self.handle_tools_list(request.params).await,
             "tools/call" => self.handle_tools_call(request.params).await,
+            "tools/call_batch" => self.handle_tools_call_batch(request.params).await,
+            "ping" => self.handle_ping(),
             _ => Err(JsonRpcError {
                 code: METHOD_NOT_FOUND,
                 message: format!("Method '{}' not found", request.method),
@@ -66,9 +86,46 @@ impl RequestHandler {
         }
     }
 
+    // Blocks a tool asked for beyond its JSON - a _resource object and/or a _content
+    // list. Anything that doesn't parse as content stays in the JSON untouched.
+    fn take_extra_content(result: &mut Value) -> Vec<ContentBlock> {
+        let Some(fields) = result.as_object_mut() else {
+            return Vec::new();
+        };
+        let mut blocks = Vec::new();
+
+        if let Some(resource) = fields
+            .get(RESOURCE_KEY)
+            .and_then(|r| serde_json::from_value::<EmbeddedResource>(r.clone()).ok())
+        {
+            fields.remove(RESOURCE_KEY);
+            blocks.push(ContentBlock::Resource { resource });
+        }
+
+        if let Some(content) = fields
+            .get(CONTENT_KEY)
+            .and_then(|c| serde_json::from_value::<Vec<ContentBlock>>(c.clone()).ok())
+        {
+            fields.remove(CONTENT_KEY);
+            blocks.extend(content);
+        }
+
+        blocks
+    }
+
+    // Ping - liveness only, no side effects and no params
+    fn handle_ping(&self) -> Result<Value, JsonRpcError> {
+        let result = PingResult {
+            ok: true,
+            uptime_ms: self.started.elapsed().as_millis() as u64,
+        };
+
+        Ok(serde_json::to_value(result).unwrap())
+    }
+
     // Initialize - validate client capabilities, no negotiation
     async fn handle_initialize(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
-        let _params: InitializeParams = if let Some(p) = params {
+        let params: InitializeParams = if let Some(p) = params {
             serde_json::from_value(p).map_err(|e| JsonRpcError {
                 code: INVALID_PARAMS,
                 message: format!("Invalid initialize params: {}", e),
@@ -82,6 +139,28 @@ impl RequestHandler {
             });
         };
 
+        // Only a valid handshake counts - a malformed first attempt can be retried
+        if self.initialized.swap(true, Ordering::SeqCst) {
+            return Err(JsonRpcError {
+                code: INVALID_REQUEST,
+                message: "Server already initialized; initialize may only be sent once per session"
+                    .to_string(),
+                data: Some(json!({ "reason": "already_initialized" })),
+            });
+        }
+
+        if params.capabilities.read_only {
+            info!("Client requested a read-only session");
+            self.read_only.store(true, Ordering::SeqCst);
+        }
+
+        let mut instructions =
+            "GameCode MCP Server v2 - Direct tool integration. Configure tools in tools.yaml"
+                .to_string();
+        if let Some(warning) = self.tool_manager.empty_config_warning() {
+            instructions.push_str(&format!("\n\nWarning: {}", warning));
+        }
+
         let result = InitializeResult {
             protocol_version: "2024-11-05".to_string(),
             capabilities: ServerCapabilities {
@@ -91,18 +170,33 @@ impl RequestHandler {
                 name: "gamecode-mcp2".to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
             },
-            instructions: Some(
-                "GameCode MCP Server v2 - Direct tool integration. Configure tools in tools.yaml"
-                    .to_string(),
-            ),
+            instructions: Some(instructions),
         };
 
         Ok(serde_json::to_value(result).unwrap())
     }
 
     // List tools - LLM sees only what we explicitly configured
-    async fn handle_tools_list(&self) -> Result<Value, JsonRpcError> {
-        let tools = self.tool_manager.get_mcp_tools();
+    async fn handle_tools_list(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let params: ListToolsParams = match params {
+            Some(p) => serde_json::from_value(p).map_err(|e| JsonRpcError {
+                code: INVALID_PARAMS,
+                message: format!("Invalid tools/list params: {}", e),
+                data: None,
+            })?,
+            None => ListToolsParams::default(),
+        };
+
+        let mut tools = if params.tags.is_empty() {
+            self.tool_manager.get_mcp_tools()
+        } else {
+            self.tool_manager.get_mcp_tools_filtered(&params.tags)
+        };
+
+        // Don't advertise what a read-only session would refuse
+        if self.read_only.load(Ordering::SeqCst) {
+            tools.retain(|tool| self.tool_manager.tool_read_only(&tool.name) == Some(true));
+        }
 
         let result = ListToolsResult { tools };
 
@@ -125,30 +219,158 @@ impl RequestHandler {
             });
         };
 
+        self.call_tool(params).await
+    }
+
+    // Each call is handled exactly as tools/call would handle it, so one bad call
+    // doesn't fail the rest
+    async fn handle_tools_call_batch(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let params: CallToolBatchParams = if let Some(p) = params {
+            serde_json::from_value(p).map_err(|e| JsonRpcError {
+                code: INVALID_PARAMS,
+                message: format!("Invalid tool call batch params: {}", e),
+                data: None,
+            })?
+        } else {
+            return Err(JsonRpcError {
+                code: INVALID_PARAMS,
+                message: "Missing tool call batch params".to_string(),
+                data: None,
+            });
+        };
+
+        if params.calls.len() > MAX_BATCH_CALLS {
+            return Err(JsonRpcError {
+                code: INVALID_PARAMS,
+                message: format!(
+                    "Batch of {} calls exceeds the limit of {}",
+                    params.calls.len(),
+                    MAX_BATCH_CALLS
+                ),
+                data: Some(json!({ "reason": "batch_too_large", "limit": MAX_BATCH_CALLS })),
+            });
+        }
+
+        let concurrency = params.max_concurrency.unwrap_or(1).clamp(1, MAX_BATCH_CONCURRENCY);
+        info!("Tool call batch of {} (concurrency {})", params.calls.len(), concurrency);
+
+        // buffered keeps results in call order whatever order they finish in
+        let results: Vec<Value> = stream::iter(params.calls)
+            .map(|call| async move {
+                match self.call_tool(call).await {
+                    Ok(result) => result,
+                    Err(error) => json!({ "error": error }),
+                }
+            })
+            .buffered(concurrency)
+            .collect()
+            .await;
+
+        Ok(serde_json::to_value(CallToolBatchResult { results }).unwrap())
+    }
+
+    async fn call_tool(&self, params: CallToolParams) -> Result<Value, JsonRpcError> {
+        let dry_run = params.meta.as_ref().is_some_and(|meta| meta.dry_run);
+
         // Execute only configured tools with validated parameters
-        match self
-            .tool_manager
-            .execute_tool(&params.name, params.arguments, &self.injected_values)
-            .await
+        let outcome = if self.read_only.load(Ordering::SeqCst)
+            && self.tool_manager.tool_read_only(&params.name) == Some(false)
         {
-            Ok(result) => {
-                let response = CallToolResult {
-                    content: vec![ContentBlock::Text {
+            warn!("Refused '{}' in read-only session", params.name);
+            Err(ToolError::Blocked(format!(
+                "Tool '{}' is not marked read_only and this session was initialized read-only",
+                params.name
+            )))
+        } else if dry_run {
+            info!("Dry run for tool: {}", params.name);
+            self.tool_manager
+                .explain_tool(&params.name, params.arguments, &self.injected_values)
+        } else {
+            self.tool_manager
+                .execute_tool(&params.name, params.arguments, &self.injected_values)
+                .await
+        };
+
+        match outcome {
+            Ok(mut result) => {
+                // Only tools allowed to emit blocks get them promoted - anything else,
+                // e.g. a command printing {"_content": ...}, stays text
+                let extra = if self.tool_manager.tool_content_blocks(&params.name) {
+                    Self::take_extra_content(&mut result)
+                } else {
+                    Vec::new()
+                };
+
+                // Tool output is only ever data inside a text block - it is serialized
+                // as a JSON string on one line, so it can't pose as protocol traffic.
+                // A client that parses text blocks naively still might take it for a
+                // frame, so a top-level "jsonrpc" key is moved down a level
+                if result.get("jsonrpc").is_some() {
+                    warn!(
+                        "Tool '{}' returned a JSON-RPC-shaped object; wrapping it under \"output\"",
+                        params.name
+                    );
+                    result = json!({ "output": result });
+                }
+
+                // A result that was nothing but blocks is passed through as those blocks
+                let blocks_only =
+                    !extra.is_empty() && result.as_object().is_some_and(|fields| fields.is_empty());
+                let mut content = Vec::new();
+                if !blocks_only {
+                    content.push(ContentBlock::Text {
                         text: serde_json::to_string(&result).unwrap_or_else(|_| "null".to_string()),
-                    }],
+                    });
+                }
+                content.extend(extra);
+
+                let response = CallToolResult {
+                    content,
                     is_error: None,
+                    meta: None,
                 };
 
                 Ok(serde_json::to_value(response).unwrap())
             }
+            // Bad requests are protocol errors - the client can tell them apart by data.reason
+            Err(ToolError::NotFound(name)) => Err(JsonRpcError {
+                code: INVALID_PARAMS,
+                message: format!("Tool '{}' not found", name),
+                data: Some(json!({ "reason": "tool_not_found", "tool": name })),
+            }),
+            Err(error @ (ToolError::InvalidArguments(_) | ToolError::SchemaViolations(_))) => {
+                let mut message = format!("Invalid arguments for tool '{}': {}", params.name, error);
+                let mut data = json!({ "reason": "invalid_arguments", "tool": params.name });
+                if let ToolError::SchemaViolations(violations) = &error {
+                    data["violations"] = json!(violations);
+                }
+
+                // Point the caller at usage docs so it can correct itself
+                if let Some(docs) = self.tool_manager.tool_docs(&params.name) {
+                    message.push_str(&format!(" (see {})", docs));
+                    data["docs"] = json!(docs);
+                }
+
+                Err(JsonRpcError {
+                    code: INVALID_PARAMS,
+                    message,
+                    data: Some(data),
+                })
+            }
+            // Failures of the tool itself stay in the result so the LLM can see them
             Err(e) => {
                 error!("Tool execution failed: {}", e);
 
+                let meta = match &e {
+                    ToolError::Timeout { signal, .. } => Some(json!({ "reason": "timeout", "signal": signal })),
+                    _ => None,
+                };
                 let response = CallToolResult {
                     content: vec![ContentBlock::Text {
                         text: format!("Error: {}", e),
                     }],
                     is_error: Some(true),
+                    meta,
                 };
 
                 Ok(serde_json::to_value(response).unwrap())
@@ -4,7 +4,8 @@
 use anyhow::Result;
 use serde_json::Value;
 use std::collections::HashMap;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use std::io::IsTerminal;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tracing::{debug, error, info, warn};
 
 mod handlers;
@@ -16,12 +17,25 @@ use handlers::RequestHandler;
 use protocol::*;
 use tools::ToolManager;
 
+// Largest request line we buffer before discarding it
+const DEFAULT_MAX_LINE_BYTES: usize = 16 * 1024 * 1024;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Handle command-line arguments
     let args: Vec<String> = std::env::args().collect();
-    let mut tools_file_override: Option<String> = None;
+    let mut tools_file_overrides: Vec<String> = Vec::new();
     let mut injected_values: HashMap<String, String> = HashMap::new();
+    let mut max_line_bytes = DEFAULT_MAX_LINE_BYTES;
+    let mut log_unknown_args = false;
+    let mut echo_calls = false;
+    let mut max_includes: Option<usize> = None;
+    let mut max_result_chars: Option<usize> = None;
+    let mut secrets_file: Option<String> = None;
+    let mut strict = false;
+    let mut allow_shell = false;
+    let mut json_logs = false;
+    let mut check = false;
     
     let mut i = 1;
     while i < args.len() {
@@ -36,13 +50,89 @@ async fn main() -> Result<()> {
             }
             "--tools-file" | "-t" => {
                 if i + 1 < args.len() {
-                    tools_file_override = Some(args[i + 1].clone());
+                    tools_file_overrides.push(args[i + 1].clone());
                     i += 2;
                 } else {
                     eprintln!("Error: --tools-file requires an argument");
                     std::process::exit(1);
                 }
             }
+            "--secrets-file" => {
+                if i + 1 < args.len() {
+                    secrets_file = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("Error: --secrets-file requires an argument");
+                    std::process::exit(1);
+                }
+            }
+            "--log-format" => {
+                match args.get(i + 1).map(String::as_str) {
+                    Some("json") => json_logs = true,
+                    Some("human") => json_logs = false,
+                    _ => {
+                        eprintln!("Error: --log-format requires 'human' or 'json'");
+                        std::process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--check" => {
+                check = true;
+                i += 1;
+            }
+            "--allow-shell" => {
+                allow_shell = true;
+                i += 1;
+            }
+            "--strict" => {
+                strict = true;
+                i += 1;
+            }
+            "--log-unknown-args" => {
+                log_unknown_args = true;
+                i += 1;
+            }
+            "--echo-calls" => {
+                echo_calls = true;
+                i += 1;
+            }
+            "--max-line-bytes" => {
+                match args.get(i + 1).and_then(|v| v.parse::<usize>().ok()) {
+                    Some(limit) if limit > 0 => {
+                        max_line_bytes = limit;
+                        i += 2;
+                    }
+                    _ => {
+                        eprintln!("Error: --max-line-bytes requires a positive number");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--max-includes" => {
+                match args.get(i + 1).and_then(|v| v.parse::<usize>().ok()) {
+                    Some(limit) => {
+                        max_includes = Some(limit);
+                        i += 2;
+                    }
+                    None => {
+                        eprintln!("Error: --max-includes requires a number");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--max-result-chars" => {
+                match args.get(i + 1).and_then(|v| v.parse::<usize>().ok()) {
+                    Some(limit) if limit > 0 => {
+                        max_result_chars = Some(limit);
+                        i += 2;
+                    }
+                    _ => {
+                        eprintln!("Error: --max-result-chars requires a positive number");
+                        std::process::exit(1);
+                    }
+                }
+            }
             "--inject" | "-i" => {
                 if i + 1 < args.len() {
                     let inject_arg = &args[i + 1];
@@ -67,23 +157,57 @@ async fn main() -> Result<()> {
     }
     
     // Tracing to stderr only - stdout is reserved for JSON-RPC protocol
-    tracing_subscriber::fmt()
+    let logs = tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("mcp_server=info".parse().unwrap()),
+                .add_directive("mcp_server=info".parse().unwrap())
+                .add_directive("audit=info".parse().unwrap()),
         )
-        .with_writer(std::io::stderr)
-        .init();
+        .with_writer(std::io::stderr);
+    if json_logs {
+        logs.json().init();
+    } else {
+        logs.init();
+    }
 
     info!("Starting GameCode MCP Server v2...");
 
     // Tool loading with clear precedence
     let mut tool_manager = ToolManager::new();
+    tool_manager.set_log_unknown_args(log_unknown_args);
+    tool_manager.set_echo_calls(echo_calls);
+    if let Some(limit) = max_includes {
+        tool_manager.set_max_total_includes(limit);
+    }
+    if let Some(limit) = max_result_chars {
+        tool_manager.set_max_result_chars(limit);
+    }
+    tool_manager.set_strict(strict);
+    tool_manager.set_allow_shell(allow_shell);
+
+    // A secrets file that was asked for but can't be read is fatal - tools would
+    // otherwise fail later, one call at a time
+    if let Some(path) = secrets_file.or_else(|| std::env::var("GAMECODE_SECRETS_FILE").ok())
+        && let Err(e) = tool_manager.load_secrets_file(std::path::Path::new(&path)).await
+    {
+        error!("{:#}", e);
+        std::process::exit(1);
+    }
     
+    if check {
+        let ok = check_config(&mut tool_manager, tools_file_overrides).await;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
     // Load tools with new precedence order
-    if let Err(e) = tool_manager.load_with_precedence(tools_file_override).await {
+    if let Err(e) = tool_manager.load_with_precedence(tools_file_overrides).await {
         warn!("Failed to load tools: {}", e);
         warn!("The server will start but no tools will be available.");
+    } else if let Some(warning) = tool_manager.empty_config_warning() {
+        warn!("{}", warning);
+    }
+    for warning in tool_manager.load_warnings() {
+        warn!("{}", warning);
     }
 
     let handler = RequestHandler::new(tool_manager, injected_values);
@@ -95,16 +219,42 @@ async fn main() -> Result<()> {
     let mut stdout = stdout;
 
     info!("MCP server ready, waiting for requests...");
+    if let Some(guidance) = terminal_guidance(
+        std::io::stdin().is_terminal(),
+        std::io::stdout().is_terminal(),
+    ) {
+        eprintln!("{}", guidance);
+    }
 
     // Single-threaded message loop - one request at a time
     loop {
-        let mut line = String::new();
-        match reader.read_line(&mut line).await {
-            Ok(0) => {
+        match read_bounded_line(&mut reader, max_line_bytes).await {
+            Ok(RequestLine::Eof) => {
                 info!("Client disconnected");
                 break;
             }
-            Ok(_) => {
+            // Oversized input is dropped, not buffered - the loop keeps serving
+            Ok(RequestLine::TooLong(size)) => {
+                error!("Discarded request line of {} bytes (limit {})", size, max_line_bytes);
+                let error_response = JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: serde_json::Value::Null,
+                    result: None,
+                    error: Some(JsonRpcError {
+                        code: PARSE_ERROR,
+                        message: format!("Request line exceeds {} bytes", max_line_bytes),
+                        data: Some(serde_json::json!({
+                            "reason": "line_too_long",
+                            "limit": max_line_bytes
+                        })),
+                    }),
+                };
+                let response_str = serde_json::to_string(&error_response)?;
+                stdout.write_all(response_str.as_bytes()).await?;
+                stdout.write_all(b"\n").await?;
+                stdout.flush().await?;
+            }
+            Ok(RequestLine::Complete(line)) => {
                 let line = line.trim();
                 if line.is_empty() {
                     continue;
@@ -187,6 +337,107 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+enum RequestLine {
+    Eof,
+    Complete(String),
+    // Total size of the discarded line
+    TooLong(usize),
+}
+
+// read_line without an unbounded buffer: past max_bytes the rest of the line
+// is consumed and thrown away so the next request starts cleanly
+async fn read_bounded_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_bytes: usize,
+) -> std::io::Result<RequestLine> {
+    let mut buf = Vec::new();
+    let mut total = 0;
+    let mut too_long = false;
+
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            if total == 0 {
+                return Ok(RequestLine::Eof);
+            }
+            break;
+        }
+
+        let (chunk_len, done) = match available.iter().position(|b| *b == b'\n') {
+            Some(pos) => (pos + 1, true),
+            None => (available.len(), false),
+        };
+        let content_len = chunk_len - usize::from(done);
+
+        total += content_len;
+        if total > max_bytes {
+            too_long = true;
+            buf = Vec::new();
+        } else {
+            buf.extend_from_slice(&available[..chunk_len]);
+        }
+
+        reader.consume(chunk_len);
+        if done {
+            break;
+        }
+    }
+
+    if too_long {
+        return Ok(RequestLine::TooLong(total));
+    }
+
+    String::from_utf8(buf)
+        .map(RequestLine::Complete)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+// --check: load the config as the server would, schema-check each top-level file,
+// and report everything on stdout instead of serving
+async fn check_config(tool_manager: &mut ToolManager, tools_file_overrides: Vec<String>) -> bool {
+    let load_result = tool_manager.load_with_precedence(tools_file_overrides).await;
+    let mut ok = true;
+
+    for source in tool_manager.sources() {
+        let Ok(content) = std::fs::read_to_string(source) else {
+            continue;
+        };
+        let report = ToolManager::validate_config_schema(&content);
+        if !report.errors.is_empty() || !report.warnings.is_empty() {
+            println!("{}:", source.display());
+        }
+        for error in &report.errors {
+            ok = false;
+            println!("  - {}", error);
+        }
+        // Ignored by the loader, but usually a typo
+        for warning in &report.warnings {
+            println!("  - Warning: {}", warning);
+        }
+    }
+
+    if let Err(e) = &load_result {
+        ok = false;
+        println!("Failed to load tools: {:#}", e);
+    }
+    for warning in tool_manager.load_warnings() {
+        println!("Warning: {}", warning);
+    }
+
+    if ok {
+        println!("Config OK: {} tools", tool_manager.get_mcp_tools().len());
+    }
+    ok
+}
+
+// Someone running the server directly in a terminal would otherwise see nothing
+// happen - a client always connects both ends with pipes
+fn terminal_guidance(stdin_is_terminal: bool, stdout_is_terminal: bool) -> Option<&'static str> {
+    (stdin_is_terminal || stdout_is_terminal).then_some(
+        "This is an MCP stdio server; it expects JSON-RPC on stdin. Try --help.",
+    )
+}
+
 fn print_help() {
     println!("gamecode-mcp2 {}", env!("CARGO_PKG_VERSION"));
     println!("{}", env!("CARGO_PKG_DESCRIPTION"));
@@ -195,10 +446,20 @@ fn print_help() {
     println!("    gamecode-mcp2 [OPTIONS]");
     println!();
     println!("OPTIONS:");
-    println!("    -h, --help               Print help information");
-    println!("    -V, --version            Print version information");
-    println!("    -t, --tools-file <FILE>  Specify tools configuration file");
-    println!("    -i, --inject KEY=VALUE   Inject server-side values (can be used multiple times)");
+    println!("    -h, --help                  Print help information");
+    println!("    -V, --version               Print version information");
+    println!("    -t, --tools-file <FILE>     Specify tools configuration file (can be used multiple times)");
+    println!("    -i, --inject KEY=VALUE      Inject server-side values (can be used multiple times)");
+    println!("        --secrets-file <FILE>   KEY=VALUE file for ${{VAR}} references in tool env and defaults");
+    println!("        --log-format <FORMAT>   Log format on stderr: human (default) or json");
+    println!("        --check                 Validate the tools configuration, report every problem, and exit");
+    println!("        --strict                Fail to load when a tool name is defined twice");
+    println!("        --allow-shell           Allow tools that set shell: true to run through sh -c");
+    println!("        --log-unknown-args      Log and drop undeclared arguments sent in tools/call");
+    println!("        --echo-calls            Include the tool name and params with every tool result");
+    println!("        --max-line-bytes <N>    Reject request lines longer than N bytes (default: 16 MiB)");
+    println!("        --max-includes <N>      Fail to load after N included files in total (default: 100)");
+    println!("        --max-result-chars <N>  Truncate tool results longer than N characters (default: no limit)");
     println!();
     println!("DESCRIPTION:");
     println!("    An MCP server that communicates via stdio (stdin/stdout).");
@@ -208,8 +469,9 @@ fn print_help() {
     println!("    Claude Desktop. It does not accept network connections.");
     println!();
     println!("ENVIRONMENT:");
-    println!("    GAMECODE_TOOLS_FILE    Path to tools YAML configuration");
+    println!("    GAMECODE_TOOLS_FILE    Path to tools YAML configuration (comma-separated for several)");
     println!("    GAMECODE_MODE          Load a specific mode/profile");
+    println!("    GAMECODE_SECRETS_FILE  Secrets file, when --secrets-file isn't given");
     println!("    RUST_LOG               Set logging level (default: info)");
     println!();
     println!("EXAMPLES:");
@@ -228,3 +490,16 @@ fn print_help() {
     println!("MORE INFO:");
     println!("    Repository: {}", env!("CARGO_PKG_REPOSITORY"));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terminal_guidance() {
+        assert!(terminal_guidance(true, true).unwrap().contains("Try --help"));
+        assert!(terminal_guidance(true, false).is_some());
+        assert!(terminal_guidance(false, true).is_some());
+        assert_eq!(terminal_guidance(false, false), None);
+    }
+}
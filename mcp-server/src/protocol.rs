@@ -54,6 +54,9 @@ pub struct InitializeParams {
 pub struct ClientCapabilities {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<ToolsCapability>,
+    // Client asks for a session limited to tools marked read_only
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,6 +90,13 @@ pub struct ServerInfo {
     pub version: String,
 }
 
+// Liveness check - answered without touching tools or requiring initialize
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PingResult {
+    pub ok: bool,
+    pub uptime_ms: u64,
+}
+
 // Tool schema is explicit - LLM sees exactly what we define
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tool {
@@ -94,6 +104,15 @@ pub struct Tool {
     pub description: String,
     #[serde(rename = "inputSchema")]
     pub input_schema: Value,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+// Optional tools/list filter - no params means every tool
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ListToolsParams {
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -105,6 +124,31 @@ pub struct ListToolsResult {
 pub struct CallToolParams {
     pub name: String,
     pub arguments: Value,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<CallToolMeta>,
+}
+
+// tools/call_batch - independent calls in one round trip
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CallToolBatchParams {
+    pub calls: Vec<CallToolParams>,
+    // Calls run one at a time unless this is above 1 (the server caps it)
+    #[serde(rename = "maxConcurrency", default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<usize>,
+}
+
+// In call order: each entry is what tools/call would have returned, or
+// {"error": {...}} where tools/call would have been an RPC error
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CallToolBatchResult {
+    pub results: Vec<Value>,
+}
+
+// Request metadata - dryRun asks what would run instead of running it
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CallToolMeta {
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -112,6 +156,9 @@ pub struct CallToolResult {
     pub content: Vec<ContentBlock>,
     #[serde(rename = "isError", skip_serializing_if = "Option::is_none")]
     pub is_error: Option<bool>,
+    // Machine-readable detail about a failure, e.g. {"reason": "timeout", "signal": "SIGKILL"}
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -119,6 +166,27 @@ pub struct CallToolResult {
 pub enum ContentBlock {
     #[serde(rename = "text")]
     Text { text: String },
+    #[serde(rename = "image")]
+    Image {
+        // Base64-encoded image bytes
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    #[serde(rename = "resource")]
+    Resource { resource: EmbeddedResource },
+}
+
+// Embedded resource content - text for textual formats, base64 blob otherwise
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddedResource {
+    pub uri: String,
+    #[serde(rename = "mimeType", default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
 }
 
 // Standard JSON-RPC error codes - no custom extensions
@@ -2,27 +2,136 @@
 // Every tool must be explicitly configured - no implicit capabilities.
 
 use anyhow::{Context, Result};
+use base64::prelude::*;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use tokio::process::Command;
-use tracing::{debug, info};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
+use tokio::process::{Child, Command};
+use tracing::{debug, info, warn};
 
 use crate::protocol::Tool;
 use crate::validation;
 
+// Every name accepted by execute_internal_handler - checked when tools load
+pub const BUILTIN_HANDLERS: &[&str] = &[
+    "add",
+    "multiply",
+    "list_files",
+    "read_file",
+    "safe_read_file",
+    "write_file",
+    "create_graphviz_diagram",
+    "create_plantuml_diagram",
+    "__describe_config",
+    "git",
+];
+
+// The git handler's subcommands, each with the only flags it accepts. All are read-only;
+// anything that can write files or run programs (--output, --ext-diff, -c) is absent
+const GIT_SUBCOMMANDS: &[(&str, &[&str])] = &[
+    ("status", &["--short", "--branch", "--porcelain"]),
+    ("log", &["--oneline", "--stat", "--name-only"]),
+    ("diff", &["--stat", "--cached", "--name-only"]),
+    ("show", &["--stat", "--name-only"]),
+];
+
+// Output formats each diagram renderer is asked for - the value goes into its argv
+// and the output filename, so nothing else is passed through
+const GRAPHVIZ_FORMATS: &[&str] = &["png", "svg", "pdf", "jpg", "jpeg"];
+const PLANTUML_FORMATS: &[&str] = &["png", "svg", "pdf"];
+
+// Result key a tool uses to return an MCP embedded resource alongside its JSON
+pub const RESOURCE_KEY: &str = "_resource";
+// Result key for a list of extra content blocks (text, image, resource)
+pub const CONTENT_KEY: &str = "_content";
+
+// Upper bound for read_file when a tool doesn't set max_read_bytes
+const DEFAULT_MAX_READ_BYTES: u64 = 1024 * 1024;
+
+// list_files recursion depth when max_depth isn't given, and the most it may ask for
+const DEFAULT_LIST_DEPTH: u64 = 3;
+const MAX_LIST_DEPTH: u64 = 10;
+
+// Included files allowed across one ToolManager's loads - also stops include cycles
+const DEFAULT_MAX_TOTAL_INCLUDES: usize = 100;
+
+// How long a timed-out command gets between SIGTERM and SIGKILL
+const DEFAULT_KILL_GRACE_MS: u64 = 2000;
+
+// Stands in for values resolved from ${VAR} wherever a call is echoed or logged
+const REDACTED: &str = "***";
+
+// Why a tool call failed - the handler maps these to protocol-level responses
+#[derive(Debug)]
+pub enum ToolError {
+    // No tool with this name is configured
+    NotFound(String),
+    // Arguments were rejected before anything ran
+    InvalidArguments(String),
+    // Arguments did not match the tool's input schema - one entry per violation
+    SchemaViolations(Vec<String>),
+    // The tool ran too long - signal is what finally stopped it
+    Timeout { message: String, signal: &'static str },
+    // Policy refused the call
+    Blocked(String),
+    // The tool ran (or tried to) and failed
+    ExecutionFailed(anyhow::Error),
+}
+
+impl ToolError {
+    fn invalid_arguments(error: anyhow::Error) -> Self {
+        ToolError::InvalidArguments(format!("{:#}", error))
+    }
+}
+
+impl std::fmt::Display for ToolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolError::NotFound(name) => write!(f, "Tool '{}' not found", name),
+            ToolError::InvalidArguments(message) => write!(f, "{}", message),
+            ToolError::SchemaViolations(violations) => write!(f, "{}", violations.join("; ")),
+            ToolError::Timeout { message, .. } => write!(f, "{}", message),
+            ToolError::Blocked(message) => write!(f, "{}", message),
+            ToolError::ExecutionFailed(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+impl From<anyhow::Error> for ToolError {
+    fn from(error: anyhow::Error) -> Self {
+        ToolError::ExecutionFailed(error)
+    }
+}
+
+// What validate_config_schema found. Unknown keys are only warnings: the loader
+// ignores them, so they can't stop a config from loading
+#[derive(Debug, Default)]
+pub struct ConfigReport {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
 // Tools config - what tools exist is controlled by YAML, not code
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ToolsConfig {
     #[serde(default)]
     pub include: Vec<String>,
+    // Shared fields that tools pull in with `extends: <template>`
     #[serde(default)]
-    pub tools: Vec<ToolDefinition>,
+    pub templates: HashMap<String, Value>,
+    // Kept raw until templates are applied, then parsed as ToolDefinition
+    #[serde(default)]
+    pub tools: Vec<Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct ToolDefinition {
     pub name: String,
     pub description: String,
@@ -37,9 +146,63 @@ pub struct ToolDefinition {
     pub example_output: Option<Value>,
     #[serde(default)]
     pub validation: ValidationConfig,
+    // Emit flagged args first, then "--", then positional args
+    #[serde(default)]
+    pub use_arg_separator: bool,
+    // JSONPath into the result, e.g. "$.data.items[0]" - only that value is returned
+    pub output_path: Option<String>,
+    // Free-form labels used to filter tools/list
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Usage reference (URL or local path) shown with argument errors
+    #[serde(default)]
+    pub docs: Option<String>,
+    // Wrap the result as {tool, params, result} so the model sees which call produced it
+    #[serde(default)]
+    pub echo_call: bool,
+    // Changes nothing - the only kind of tool a read-only session may call
+    #[serde(default)]
+    pub read_only: bool,
+    // External commands only: stop the command after this long (SIGTERM, then SIGKILL)
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    // Time between SIGTERM and SIGKILL on timeout (default 2000)
+    #[serde(default)]
+    pub kill_grace_ms: Option<u64>,
+    // tools/list order: highest first, ties by name - so key tools are seen first
+    #[serde(default)]
+    pub priority: i32,
+    // External commands only: extra environment for the command. Values may
+    // reference ${VAR}, resolved from the secrets file and then the environment
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    // Opt-in: `command` is an `sh -c` script with {arg} placeholders, each replaced
+    // by the single-quoted value. Needs the server's --allow-shell
+    #[serde(default)]
+    pub shell: bool,
+    // Shell mode rejects metacharacters in values unless this is set - quoting
+    // still passes them to the script as literal text
+    #[serde(default)]
+    pub allow_shell_metacharacters: bool,
+    // How stdout becomes the result: "raw" (default - JSON if it parses, else
+    // {output, status}), "lines", "json" (must parse), or "regex"
+    #[serde(default)]
+    #[schemars(extend("enum" = ["raw", "lines", "json", "regex", null]))]
+    pub output_parser: Option<String>,
+    // "regex" parser only: each match becomes an object of its named captures
+    #[serde(default)]
+    pub output_pattern: Option<String>,
+    // Longest result returned, in characters - overrides --max-result-chars
+    #[serde(default)]
+    pub max_result_chars: Option<usize>,
+    // External commands only: turn _content/_resource in the JSON output into MCP
+    // content blocks. Off by default - a command's output is data, and a file it
+    // prints must not be able to pose as server-produced images or resources
+    #[serde(default)]
+    pub content_blocks: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
 pub struct ValidationConfig {
     #[serde(default)]
     pub validate_paths: bool,
@@ -47,25 +210,74 @@ pub struct ValidationConfig {
     pub allow_absolute_paths: bool,
     #[serde(default)]  
     pub validate_args: bool,
+    // When set, path arguments must resolve (through symlinks) inside this directory
+    #[serde(default)]
+    pub allowed_root: Option<String>,
+    // Largest read allowed by file-reading handlers (default 1 MiB)
+    #[serde(default)]
+    pub max_read_bytes: Option<u64>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct ArgDefinition {
     pub name: String,
     pub description: String,
     pub required: bool,
     #[serde(rename = "type")]
+    #[schemars(extend("enum" = ["string", "number", "boolean", "array", "keyvalue"]))]
     pub arg_type: String,
     pub cli_flag: Option<String>,
-    #[allow(dead_code)]
-    pub default: Option<String>,
+    pub default: Option<String>,  // Used when the caller omits the argument
     #[serde(default)]
     pub is_path: bool,  // Mark arguments that are file paths
+    #[serde(default)]
+    pub expand_globs: bool,  // Expand *, ? and [..] without invoking a shell
+    // Value constraints - advertised in the schema and enforced before execution
+    #[serde(default)]
+    pub minimum: Option<f64>,
+    #[serde(default)]
+    pub maximum: Option<f64>,
+    #[serde(default)]
+    pub enum_values: Vec<String>,
+    #[serde(default)]
+    pub pattern: Option<String>,
+    // Order among positional args (lowest first), independent of where the arg is listed
+    #[serde(default)]
+    pub position: Option<usize>,
+    // "presence": a boolean flag is emitted alone when true and omitted when false.
+    // Default ("value") passes "true"/"false" after the flag
+    #[serde(default)]
+    #[schemars(extend("enum" = ["value", "presence", null]))]
+    pub flag_style: Option<String>,
 }
 
 #[derive(Default)]
 pub struct ToolManager {
     tools: HashMap<String, ToolDefinition>,
+    // Top-level config files, in load order - for operator-facing messages
+    sources: Vec<PathBuf>,
+    // Templates seen so far - included files can provide them
+    templates: HashMap<String, Value>,
+    // Audit-log argument names callers send that the tool doesn't declare
+    log_unknown_args: bool,
+    // echo_call for every tool
+    echo_calls: bool,
+    // File each tool came from, and which top-level source pulled it in
+    origins: HashMap<String, (PathBuf, usize)>,
+    // Duplicate names are a load error instead of a warning
+    strict: bool,
+    warnings: Vec<String>,
+    // Project type picked by auto-detection, if that's how tools were found
+    mode: Option<String>,
+    // Include budget (None uses the default) and how much of it is spent
+    max_total_includes: Option<usize>,
+    includes_loaded: usize,
+    // From --secrets-file - only reachable through ${VAR}, never set on the server process
+    secrets: HashMap<String, String>,
+    // Whether shell-mode tools may be loaded at all
+    allow_shell: bool,
+    // Result size cap for tools that don't set max_result_chars
+    max_result_chars: Option<usize>,
 }
 
 impl ToolManager {
@@ -73,28 +285,188 @@ impl ToolManager {
         Self::default()
     }
 
+    // Top-level config files loaded so far, in order
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+
+    // Check a config file's text against the config format schema and report every
+    // problem at once - serde stops at the first. Tool-level problems name the tool
+    // and the line it starts on. Only the file itself is checked, not its includes
+    pub fn validate_config_schema(content: &str) -> ConfigReport {
+        let mut report = ConfigReport::default();
+
+        // JSON configs parse as YAML too
+        let config: Value = match serde_yaml::from_str(content) {
+            Ok(config) => config,
+            Err(e) => {
+                report.errors.push(e.to_string());
+                return report;
+            }
+        };
+
+        let schema = config_schema();
+        let validator = match jsonschema::validator_for(&schema) {
+            Ok(validator) => validator,
+            Err(e) => {
+                report.errors.push(format!("Invalid config schema: {}", e));
+                return report;
+            }
+        };
+
+        for e in validator.iter_errors(&config) {
+            let unknown_key = matches!(e.kind, jsonschema::error::ValidationErrorKind::AdditionalProperties { .. });
+            let message = {
+                let location = e.instance_path.to_string();
+                let mut message = if location.is_empty() {
+                    e.to_string()
+                } else {
+                    format!("{}: {}", location, e)
+                };
+
+                // "/tools/3/..." - point at the tool in the file
+                let tool = location
+                    .strip_prefix("/tools/")
+                    .and_then(|rest| rest.split('/').next())
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .and_then(|index| config["tools"][index]["name"].as_str());
+                if let Some(name) = tool {
+                    match tool_line(content, name) {
+                        Some(line) => message.push_str(&format!(" (tool '{}', line {})", name, line)),
+                        None => message.push_str(&format!(" (tool '{}')", name)),
+                    }
+                }
+                message
+            };
+
+            if unknown_key {
+                report.warnings.push(message);
+            } else {
+                report.errors.push(message);
+            }
+        }
+
+        report
+    }
+
+    // Whether _content/_resource in this tool's result become content blocks -
+    // always for internal handlers, opt-in for commands
+    pub fn tool_content_blocks(&self, name: &str) -> bool {
+        self.tools
+            .get(name)
+            .is_some_and(|tool| tool.internal_handler.is_some() || tool.content_blocks)
+    }
+
+    // None for an unknown tool
+    pub fn tool_read_only(&self, name: &str) -> Option<bool> {
+        self.tools.get(name).map(|tool| tool.read_only)
+    }
+
+    pub fn tool_docs(&self, name: &str) -> Option<&str> {
+        self.tools.get(name).and_then(|tool| tool.docs.as_deref())
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    // Problems found while loading that didn't stop the load
+    pub fn load_warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn set_log_unknown_args(&mut self, enabled: bool) {
+        self.log_unknown_args = enabled;
+    }
+
+    pub fn set_echo_calls(&mut self, enabled: bool) {
+        self.echo_calls = enabled;
+    }
+
+    pub fn set_max_total_includes(&mut self, limit: usize) {
+        self.max_total_includes = Some(limit);
+    }
+
+    pub fn set_allow_shell(&mut self, allowed: bool) {
+        self.allow_shell = allowed;
+    }
+
+    pub fn set_max_result_chars(&mut self, limit: usize) {
+        self.max_result_chars = Some(limit);
+    }
+
+    // A .env-style file of KEY=VALUE lines, for ${VAR} references in tool env and defaults
+    pub async fn load_secrets_file(&mut self, path: &Path) -> Result<()> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Cannot read secrets file {}", path.display()))?;
+        self.secrets = parse_secrets(&content)
+            .with_context(|| format!("Invalid secrets file {}", path.display()))?;
+        // Counts only - values are masked in logs, echoes and dry runs
+        info!("Loaded {} secrets from {}", self.secrets.len(), path.display());
+        Ok(())
+    }
+
+    // Load several files in order - a later file's tool replaces an earlier one of the same name
+    pub async fn load_from_files(&mut self, paths: &[String]) -> Result<()> {
+        for path in paths {
+            self.load_from_file(Path::new(path)).await?;
+        }
+        Ok(())
+    }
+
     // Explicit tool loading - admin controls what tools are available
     pub async fn load_from_file(&mut self, path: &Path) -> Result<()> {
+        self.sources.push(path.to_path_buf());
+        self.load_file(path).await
+    }
+
+    async fn load_file(&mut self, path: &Path) -> Result<()> {
         info!("Loading tools from: {}", path.display());
 
         let content = tokio::fs::read_to_string(path)
             .await
             .context("Failed to read tools file")?;
 
-        // YAML parsing is the only text processing we can't avoid
-        let config: ToolsConfig = serde_yaml::from_str(&content).context("Failed to parse YAML")?;
+        let config = Self::parse_config(path, &content)?;
 
         // Process includes first
         for include in &config.include {
             let include_path = self.resolve_include_path(path, include)?;
+
+            // Every include counts, however deep, so fan-out is bounded as well as depth
+            let limit = self.max_total_includes.unwrap_or(DEFAULT_MAX_TOTAL_INCLUDES);
+            if self.includes_loaded >= limit {
+                return Err(anyhow::anyhow!(
+                    "Too many included files (limit {}) while including '{}' from {}",
+                    limit,
+                    include,
+                    path.display()
+                ));
+            }
+            self.includes_loaded += 1;
             info!("Including tools from: {}", include_path.display());
 
             // Recursively load included files
-            Box::pin(self.load_from_file(&include_path)).await?;
+            Box::pin(self.load_file(&include_path)).await?;
         }
 
+        self.templates.extend(config.templates);
+
         // Then load tools from this file
-        for tool in config.tools {
+        for raw in config.tools {
+            let tool = self
+                .resolve_tool(raw)
+                .with_context(|| format!("Invalid tool definition in {}", path.display()))?;
+            Self::validate_definition(&tool)?;
+            if tool.shell && !self.allow_shell {
+                return Err(anyhow::anyhow!(
+                    "Tool '{}' in {} uses shell mode, which is off unless the server is started with --allow-shell",
+                    tool.name,
+                    path.display()
+                ));
+            }
+            self.record_origin(&tool.name, path)?;
             info!("Loaded tool: {}", tool.name);
             self.tools.insert(tool.name.clone(), tool);
         }
@@ -102,6 +474,270 @@ impl ToolManager {
         Ok(())
     }
 
+    // Redefining a tool from an earlier --tools-file is an override; doing it within
+    // one file and its includes is almost always a mistake
+    fn record_origin(&mut self, name: &str, path: &Path) -> Result<()> {
+        let source_index = self.sources.len().saturating_sub(1);
+
+        if let Some((previous, previous_index)) = self.origins.get(name) {
+            if *previous_index != source_index {
+                info!(
+                    "Tool '{}' from {} overridden by {}",
+                    name,
+                    previous.display(),
+                    path.display()
+                );
+            } else {
+                let message = format!(
+                    "Duplicate tool '{}': defined in {} and again in {}; the later definition wins",
+                    name,
+                    previous.display(),
+                    path.display()
+                );
+                if self.strict {
+                    return Err(anyhow::anyhow!(message));
+                }
+                self.warnings.push(message);
+            }
+        }
+
+        self.origins
+            .insert(name.to_string(), (path.to_path_buf(), source_index));
+        Ok(())
+    }
+
+    // Apply `extends` - tool fields win over the template, args lists are concatenated
+    fn resolve_tool(&self, raw: Value) -> Result<ToolDefinition> {
+        let Value::Object(mut fields) = raw else {
+            return Err(anyhow::anyhow!("Tool definition must be a mapping"));
+        };
+
+        if let Some(extends) = fields.remove("extends") {
+            let template_name = extends
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("'extends' must be a template name"))?;
+            let template = self
+                .templates
+                .get(template_name)
+                .and_then(|t| t.as_object())
+                .ok_or_else(|| anyhow::anyhow!("Unknown template '{}'", template_name))?;
+
+            let mut merged = template.clone();
+            for (key, value) in fields {
+                match (merged.get_mut(&key), value) {
+                    (Some(Value::Array(base)), Value::Array(extra)) if key == "args" => {
+                        base.extend(extra);
+                    }
+                    (_, value) => {
+                        merged.insert(key, value);
+                    }
+                }
+            }
+            fields = merged;
+        }
+
+        let name = fields
+            .get("name")
+            .and_then(|v| v.as_str())
+            .unwrap_or("<unnamed>")
+            .to_string();
+        serde_json::from_value(Value::Object(fields))
+            .with_context(|| format!("Failed to parse tool '{}'", name))
+    }
+
+    // A config that parsed but defines nothing is not the same as no config
+    pub fn empty_config_warning(&self) -> Option<String> {
+        if self.sources.is_empty() || !self.tools.is_empty() {
+            return None;
+        }
+
+        let sources: Vec<String> = self
+            .sources
+            .iter()
+            .map(|source| source.display().to_string())
+            .collect();
+        Some(format!(
+            "Loaded config from {} but it defines no tools",
+            sources.join(", ")
+        ))
+    }
+
+    // Config mistakes fail the load instead of surfacing on first call
+    fn validate_definition(tool: &ToolDefinition) -> Result<()> {
+        // Catch defaults that can't be converted to their declared type
+        for arg_def in &tool.args {
+            let default = Self::typed_default(arg_def)
+                .with_context(|| format!("Invalid default in tool '{}'", tool.name))?;
+
+            // Constraints must compile (e.g. a bad pattern) and the default must satisfy them
+            let validator = jsonschema::validator_for(&Self::arg_schema(arg_def)).map_err(|e| {
+                anyhow::anyhow!(
+                    "Invalid constraints for argument '{}' in tool '{}': {}",
+                    arg_def.name,
+                    tool.name,
+                    e
+                )
+            })?;
+            if let Some(default) = default
+                && let Err(e) = validator.validate(&default)
+            {
+                return Err(anyhow::anyhow!(
+                    "Default for argument '{}' in tool '{}' violates its constraints: {}",
+                    arg_def.name,
+                    tool.name,
+                    e
+                ));
+            }
+        }
+
+        for arg_def in &tool.args {
+            match arg_def.flag_style.as_deref() {
+                None | Some("value") => {}
+                Some("presence") if arg_def.arg_type == "boolean" && arg_def.cli_flag.is_some() => {}
+                Some("presence") => {
+                    return Err(anyhow::anyhow!(
+                        "Argument '{}' in tool '{}' uses flag_style 'presence', which needs type boolean and a cli_flag",
+                        arg_def.name,
+                        tool.name
+                    ));
+                }
+                Some(other) => {
+                    return Err(anyhow::anyhow!(
+                        "Argument '{}' in tool '{}' has unknown flag_style '{}' (expected 'value' or 'presence')",
+                        arg_def.name,
+                        tool.name,
+                        other
+                    ));
+                }
+            }
+        }
+
+        // Positions only order positional args, and two args can't share one
+        let mut positions = HashMap::new();
+        for arg_def in &tool.args {
+            let Some(position) = arg_def.position else {
+                continue;
+            };
+            if arg_def.cli_flag.is_some() {
+                return Err(anyhow::anyhow!(
+                    "Argument '{}' in tool '{}' has both cli_flag and position; position only applies to positional args",
+                    arg_def.name,
+                    tool.name
+                ));
+            }
+            if let Some(other) = positions.insert(position, &arg_def.name) {
+                return Err(anyhow::anyhow!(
+                    "Arguments '{}' and '{}' in tool '{}' share position {}",
+                    other,
+                    arg_def.name,
+                    tool.name,
+                    position
+                ));
+            }
+        }
+
+        if let Some(key) = tool.env.keys().find(|key| key.is_empty() || key.contains(['=', '\0'])) {
+            return Err(anyhow::anyhow!("Tool '{}' has invalid env name '{}'", tool.name, key));
+        }
+
+        // Shell tools only place values through {arg} placeholders
+        if tool.shell {
+            if tool.internal_handler.is_some() {
+                return Err(anyhow::anyhow!("Tool '{}' can't use shell mode with an internal_handler", tool.name));
+            }
+            if !tool.static_flags.is_empty()
+                || tool.use_arg_separator
+                || tool.args.iter().any(|arg_def| arg_def.cli_flag.is_some() || arg_def.position.is_some())
+            {
+                return Err(anyhow::anyhow!(
+                    "Tool '{}' uses shell mode, where static_flags, use_arg_separator, cli_flag and position don't apply; put {{arg}} placeholders in the command instead",
+                    tool.name
+                ));
+            }
+        }
+
+        match (tool.output_parser.as_deref(), &tool.output_pattern) {
+            (None | Some("raw" | "lines" | "json"), None) => {}
+            (Some("regex"), Some(pattern)) => {
+                let regex = regex::Regex::new(pattern).map_err(|e| {
+                    anyhow::anyhow!("Invalid output_pattern in tool '{}': {}", tool.name, e)
+                })?;
+                if regex.capture_names().flatten().next().is_none() {
+                    return Err(anyhow::anyhow!(
+                        "output_pattern in tool '{}' has no named captures, e.g. (?P<name>...)",
+                        tool.name
+                    ));
+                }
+            }
+            (Some("regex"), None) => {
+                return Err(anyhow::anyhow!("Tool '{}' uses output_parser 'regex' without an output_pattern", tool.name));
+            }
+            (Some("raw" | "lines" | "json") | None, Some(_)) => {
+                return Err(anyhow::anyhow!("Tool '{}' sets output_pattern, which needs output_parser 'regex'", tool.name));
+            }
+            (Some(other), _) => {
+                return Err(anyhow::anyhow!(
+                    "Tool '{}' has unknown output_parser '{}' (expected 'raw', 'lines', 'json' or 'regex')",
+                    tool.name,
+                    other
+                ));
+            }
+        }
+
+        // Exactly one execution mechanism - "command: internal" marks handler tools
+        let has_command = !tool.command.is_empty() && tool.command != "internal";
+        match (&tool.internal_handler, has_command) {
+            (Some(handler), true) => {
+                return Err(anyhow::anyhow!(
+                    "Tool '{}' sets both command '{}' and internal_handler '{}'; use one or the other",
+                    tool.name,
+                    tool.command,
+                    handler
+                ));
+            }
+            (None, false) => {
+                return Err(anyhow::anyhow!(
+                    "Tool '{}' has neither a command nor an internal_handler",
+                    tool.name
+                ));
+            }
+            _ => {}
+        }
+
+        if let Some(output_path) = &tool.output_path {
+            parse_output_path(output_path)
+                .with_context(|| format!("Invalid output_path in tool '{}'", tool.name))?;
+        }
+
+        if let Some(handler) = &tool.internal_handler
+            && !BUILTIN_HANDLERS.contains(&handler.as_str())
+        {
+            return Err(anyhow::anyhow!(
+                "Tool '{}' uses unknown internal handler '{}'. Valid handlers: {}",
+                tool.name,
+                handler,
+                BUILTIN_HANDLERS.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Format is chosen by extension - anything that isn't .json is treated as YAML
+    fn parse_config(path: &Path, content: &str) -> Result<ToolsConfig> {
+        let is_json = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+        if is_json {
+            serde_json::from_str(content).context("Failed to parse JSON")
+        } else {
+            // YAML parsing is the only text processing we can't avoid
+            serde_yaml::from_str(content).context("Failed to parse YAML")
+        }
+    }
+
     fn resolve_include_path(&self, base_path: &Path, include: &str) -> Result<PathBuf> {
         let base_dir = base_path
             .parent()
@@ -137,18 +773,24 @@ impl ToolManager {
     }
 
 
-    pub async fn load_with_precedence(&mut self, cli_override: Option<String>) -> Result<()> {
+    pub async fn load_with_precedence(&mut self, cli_overrides: Vec<String>) -> Result<()> {
         // Clear precedence order:
-        // 1. Command-line flag (--tools-file)
-        if let Some(tools_file) = cli_override {
-            info!("Loading tools from command-line override: {}", tools_file);
-            return self.load_from_file(Path::new(&tools_file)).await;
+        // 1. Command-line flag (--tools-file, repeatable)
+        if !cli_overrides.is_empty() {
+            info!("Loading tools from command-line override: {}", cli_overrides.join(", "));
+            return self.load_from_files(&cli_overrides).await;
         }
         
-        // 2. Environment variable
-        if let Ok(tools_file) = std::env::var("GAMECODE_TOOLS_FILE") {
-            info!("Loading tools from GAMECODE_TOOLS_FILE: {}", tools_file);
-            return self.load_from_file(Path::new(&tools_file)).await;
+        // 2. Environment variable (comma-separated list)
+        if let Ok(tools_files) = std::env::var("GAMECODE_TOOLS_FILE") {
+            info!("Loading tools from GAMECODE_TOOLS_FILE: {}", tools_files);
+            let files: Vec<String> = tools_files
+                .split(',')
+                .map(str::trim)
+                .filter(|f| !f.is_empty())
+                .map(String::from)
+                .collect();
+            return self.load_from_files(&files).await;
         }
         
         // 3. Local tools.yaml in current directory
@@ -162,6 +804,7 @@ impl ToolManager {
         if let Ok(mode) = self.detect_project_type() {
             info!("Auto-detected {} project", mode);
             if self.load_auto_detected_tools(&mode).await.is_ok() {
+                self.mode = Some(mode);
                 return Ok(());
             }
         }
@@ -221,206 +864,1042 @@ impl ToolManager {
 
     // Convert to MCP schema - LLM sees exactly this, nothing hidden
     pub fn get_mcp_tools(&self) -> Vec<Tool> {
-        self.tools
-            .values()
-            .map(|def| {
-                let mut properties = serde_json::Map::new();
-                let mut required = Vec::new();
-
-                // Build JSON schema from arg definitions
-                for arg in &def.args {
-                    let arg_schema = match arg.arg_type.as_str() {
-                        "string" => json!({
-                            "type": "string",
-                            "description": arg.description
-                        }),
-                        "number" => json!({
-                            "type": "number",
-                            "description": arg.description
-                        }),
-                        "boolean" => json!({
-                            "type": "boolean",
-                            "description": arg.description
-                        }),
-                        "array" => json!({
-                            "type": "array",
-                            "description": arg.description
-                        }),
-                        _ => json!({
-                            "type": "string",
-                            "description": arg.description
-                        }),
-                    };
+        self.get_mcp_tools_filtered(&[])
+    }
 
-                    properties.insert(arg.name.clone(), arg_schema);
+    // Tools carrying any of the given tags - an empty filter means every tool
+    pub fn get_mcp_tools_filtered(&self, tags: &[String]) -> Vec<Tool> {
+        let mut defs: Vec<&ToolDefinition> = self
+            .tools
+            .values()
+            .filter(|def| tags.is_empty() || def.tags.iter().any(|tag| tags.contains(tag)))
+            .collect();
+        defs.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.name.cmp(&b.name)));
 
-                    if arg.required {
-                        required.push(json!(arg.name));
-                    }
+        defs.into_iter()
+            .map(|def| {
+                // Tags and docs travel in _meta so clients can group tools themselves
+                let mut meta = serde_json::Map::new();
+                if !def.tags.is_empty() {
+                    meta.insert("tags".to_string(), json!(def.tags));
                 }
-
-                let schema = json!({
-                    "type": "object",
-                    "properties": properties,
-                    "required": required
-                });
+                if let Some(docs) = &def.docs {
+                    meta.insert("docs".to_string(), json!(docs));
+                }
+                let meta = (!meta.is_empty()).then_some(Value::Object(meta));
 
                 Tool {
                     name: def.name.clone(),
                     description: def.description.clone(),
-                    input_schema: schema,
+                    input_schema: Self::input_schema(def),
+                    meta,
                 }
             })
             .collect()
     }
 
+    // JSON Schema for a tool's arguments - advertised in tools/list and enforced on calls
+    fn input_schema(def: &ToolDefinition) -> Value {
+        let mut properties = serde_json::Map::new();
+        let mut required = Vec::new();
+
+        // Build JSON schema from arg definitions
+        for arg in &def.args {
+            properties.insert(arg.name.clone(), Self::arg_schema(arg));
+
+            // A default makes the argument effectively optional
+            if arg.required && arg.default.is_none() {
+                required.push(json!(arg.name));
+            }
+        }
+
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false
+        })
+    }
+
+    // Schema for a single argument, including any value constraints
+    fn arg_schema(arg: &ArgDefinition) -> Value {
+        let mut arg_schema = match arg.arg_type.as_str() {
+            "string" => json!({
+                "type": "string",
+                "description": arg.description
+            }),
+            "number" => json!({
+                "type": "number",
+                "description": arg.description
+            }),
+            "boolean" => json!({
+                "type": "boolean",
+                "description": arg.description
+            }),
+            "array" => json!({
+                "type": "array",
+                "description": arg.description
+            }),
+            "keyvalue" => json!({
+                "type": "object",
+                "description": arg.description,
+                "additionalProperties": {
+                    "type": ["string", "number", "boolean"]
+                }
+            }),
+            _ => json!({
+                "type": "string",
+                "description": arg.description
+            }),
+        };
+
+        if let Ok(Some(default)) = Self::typed_default(arg) {
+            arg_schema["default"] = default;
+        }
+        if let Some(minimum) = arg.minimum {
+            arg_schema["minimum"] = json!(minimum);
+        }
+        if let Some(maximum) = arg.maximum {
+            arg_schema["maximum"] = json!(maximum);
+        }
+        if !arg.enum_values.is_empty() {
+            arg_schema["enum"] = json!(arg.enum_values);
+        }
+        if let Some(pattern) = &arg.pattern {
+            arg_schema["pattern"] = json!(pattern);
+        }
+
+        arg_schema
+    }
+
+    // Check call arguments against the advertised schema, collecting every violation
+    fn validate_schema(tool: &ToolDefinition, args: &Value) -> Result<(), ToolError> {
+        let schema = Self::input_schema(tool);
+        let validator = jsonschema::validator_for(&schema).map_err(|e| {
+            ToolError::ExecutionFailed(anyhow::anyhow!(
+                "Invalid input schema for tool '{}': {}",
+                tool.name,
+                e
+            ))
+        })?;
+
+        let violations: Vec<String> = validator
+            .iter_errors(args)
+            .map(|e| {
+                let location = e.instance_path.to_string();
+                if location.is_empty() {
+                    e.to_string()
+                } else {
+                    format!("{}: {}", location, e)
+                }
+            })
+            .collect();
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ToolError::SchemaViolations(violations))
+        }
+    }
+
+    // With --log-unknown-args, undeclared arguments are logged and dropped before
+    // validation, so the call goes ahead - the names are data for evolving the schema
+    fn drop_unknown_args(&self, tool: &ToolDefinition, mut args: Value) -> Value {
+        if self.log_unknown_args
+            && let Some(fields) = args.as_object_mut()
+        {
+            let unknown: Vec<String> = fields
+                .keys()
+                .filter(|key| !tool.args.iter().any(|arg| &arg.name == *key))
+                .cloned()
+                .collect();
+            if !unknown.is_empty() {
+                info!(
+                    target: "audit",
+                    tool = tool.name.as_str(),
+                    unknown_args = ?unknown,
+                    "Undeclared arguments in tools/call; dropped"
+                );
+                for key in &unknown {
+                    fields.remove(key);
+                }
+            }
+        }
+        args
+    }
+
     // Tool execution - the critical security boundary
-    pub async fn execute_tool(&self, name: &str, args: Value, injected_values: &HashMap<String, String>) -> Result<Value> {
+    pub async fn execute_tool(&self, name: &str, args: Value, injected_values: &HashMap<String, String>) -> Result<Value, ToolError> {
         let tool = self
             .tools
             .get(name)
-            .ok_or_else(|| anyhow::anyhow!("Tool '{}' not found", name))?;
+            .ok_or_else(|| ToolError::NotFound(name.to_string()))?;
+
+        // Defaults are filled in first so they pass through the same validation
+        let args = self.drop_unknown_args(tool, args);
+        let masked = Self::env_default_args(tool, &args);
+        let args = self
+            .apply_defaults(tool, args)
+            .map_err(ToolError::invalid_arguments)?;
+
+        // Validation runs before dispatch so internal handlers get the same checks
+        Self::validate_schema(tool, &args)?;
+        self.validate_arguments(tool, &args)
+            .map_err(ToolError::invalid_arguments)?;
 
         // Internal handlers are hardcoded - no dynamic code execution
-        if let Some(handler) = &tool.internal_handler {
-            return self.execute_internal_handler(handler, &args, injected_values).await;
+        let result = if let Some(handler) = &tool.internal_handler {
+            self.execute_internal_handler(tool, handler, &args, injected_values).await?
+        } else {
+            let argv = self
+                .command_args(tool, &args)
+                .map_err(ToolError::invalid_arguments)?;
+            // Program and argv only - the env may hold secrets
+            debug!(
+                "Executing command: {} {:?}",
+                Self::program(tool),
+                self.redact_argv(tool, &args, &masked, &argv)
+            );
+            self.execute_command(tool, argv, injected_values).await?
+        };
+
+        // Narrow the result to the part the tool is configured to return
+        let result = match &tool.output_path {
+            Some(output_path) => extract_output_path(&result, output_path)?,
+            None => result,
+        };
+
+        let blocks = self.tool_content_blocks(name);
+        let result = match tool.max_result_chars.or(self.max_result_chars) {
+            Some(limit) => truncate_result(name, result, limit, blocks),
+            None => result,
+        };
+
+        if tool.echo_call || self.echo_calls {
+            return Ok(Self::echo_envelope(name, Self::redact_args(args, &masked), result, blocks));
+        }
+
+        Ok(result)
+    }
+
+    // Params are the normalized arguments, defaults included. Extra content
+    // stays at the top level, where tools/call looks for it
+    fn echo_envelope(name: &str, params: Value, mut result: Value, blocks: bool) -> Value {
+        let mut envelope = json!({ "tool": name, "params": params });
+        if blocks && let Some(fields) = result.as_object_mut() {
+            for key in [RESOURCE_KEY, CONTENT_KEY] {
+                if let Some(value) = fields.remove(key) {
+                    envelope[key] = value;
+                }
+            }
         }
+        envelope["result"] = result;
+        envelope
+    }
 
+    async fn execute_command(&self, tool: &ToolDefinition, argv: Vec<String>, injected_values: &HashMap<String, String>) -> Result<Value, ToolError> {
         // External commands - only what's explicitly configured
         if tool.command.is_empty() || tool.command == "internal" {
-            return Err(anyhow::anyhow!("Tool '{}' has no command", name));
+            return Err(anyhow::anyhow!("Tool '{}' has no command", tool.name).into());
         }
 
-        let mut cmd = Command::new(&tool.command);
+        let mut cmd = Command::new(Self::program(tool));
         
         // Set injected values as environment variables for the command
-        for (key, value) in injected_values {
-            cmd.env(format!("GAMECODE_{}", key.to_uppercase()), value);
-        }
+        cmd.envs(Self::injected_env(injected_values));
+        cmd.envs(self.tool_env(tool)?);
 
-        // Add static flags
-        for flag in &tool.static_flags {
-            cmd.arg(flag);
+        cmd.args(argv);
+        // Relative path arguments were validated against the root, so run from there
+        if let Some(root) = &tool.validation.allowed_root {
+            cmd.current_dir(root);
         }
 
-        // Argument construction - no shell interpretation, direct args only
-        if let Some(obj) = args.as_object() {
-            for arg_def in &tool.args {
-                if let Some(value) = obj.get(&arg_def.name) {
-                    // Optional validation
-                    if tool.validation.validate_args {
-                        validation::validate_typed_value(value, &arg_def.arg_type)?;
-                    }
-                    
-                    // Path validation if marked as path
-                    if arg_def.is_path && tool.validation.validate_paths {
-                        if let Some(path_str) = value.as_str() {
-                            validation::validate_path(path_str, tool.validation.allow_absolute_paths)?;
-                        }
-                    }
-                    
-                    let arg_value = value.to_string().trim_matches('"').to_string();
-                    
-                    if let Some(cli_flag) = &arg_def.cli_flag {
-                        cmd.arg(cli_flag);
-                        cmd.arg(&arg_value);
-                    } else {
-                        // Positional argument
-                        cmd.arg(&arg_value);
+        // Its own process group, so a timeout can stop everything it started
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    anyhow::anyhow!("Command '{}' not found on PATH", Self::program(tool))
+                } else {
+                    anyhow::Error::new(e).context("Failed to execute command")
+                }
+            })?;
+
+        // Taken now - once the child is reaped it no longer reports one
+        let pid = child.id();
+
+        // Pipes are drained while we wait, so a chatty command can't block on a full pipe
+        let mut stdout = tokio::spawn(Self::read_pipe(child.stdout.take()));
+        let mut stderr = tokio::spawn(Self::read_pipe(child.stderr.take()));
+
+        // The pipes stay open as long as anything the command started holds them,
+        // so draining them is under the same deadline as the exit
+        let finished = async {
+            let status = child.wait().await.context("Failed to wait for command")?;
+            let stdout = (&mut stdout)
+                .await
+                .context("Failed to read command output")?
+                .context("Failed to read command stdout")?;
+            let stderr = (&mut stderr)
+                .await
+                .context("Failed to read command output")?
+                .context("Failed to read command stderr")?;
+            anyhow::Ok((status, stdout, stderr))
+        };
+
+        let (status, stdout, stderr) = match tool.timeout_ms {
+            Some(timeout_ms) => {
+                let outcome = tokio::time::timeout(Duration::from_millis(timeout_ms), finished).await;
+                match outcome {
+                    Ok(finished) => finished?,
+                    Err(_) => {
+                        let grace = Duration::from_millis(tool.kill_grace_ms.unwrap_or(DEFAULT_KILL_GRACE_MS));
+                        let signal = Self::terminate(&mut child, pid, grace).await;
+                        stdout.abort();
+                        stderr.abort();
+                        warn!("Tool '{}' timed out after {}ms; stopped with {}", tool.name, timeout_ms, signal);
+                        return Err(ToolError::Timeout {
+                            message: format!("Tool '{}' timed out after {}ms", tool.name, timeout_ms),
+                            signal,
+                        });
                     }
                 }
             }
+            None => finished.await?,
+        };
+
+        if status.success() {
+            let stdout = String::from_utf8_lossy(&stdout);
+            Ok(Self::parse_output(tool, &stdout)?)
+        } else {
+            let stderr = String::from_utf8_lossy(&stderr);
+            Err(anyhow::anyhow!("Command failed: {}", stderr).into())
         }
+    }
 
-        debug!("Executing command: {:?}", cmd);
+    // Parsers are checked at load, so an unknown one can't get here
+    fn parse_output(tool: &ToolDefinition, stdout: &str) -> Result<Value> {
+        match tool.output_parser.as_deref() {
+            Some("lines") => Ok(json!(stdout.lines().collect::<Vec<_>>())),
+            Some("json") => serde_json::from_str(stdout)
+                .with_context(|| format!("Output of tool '{}' is not valid JSON", tool.name)),
+            Some("regex") => {
+                let pattern = tool.output_pattern.as_deref().unwrap_or_default();
+                // ^ and $ match at line boundaries - command output is line-oriented
+                let regex = regex::RegexBuilder::new(pattern).multi_line(true).build()?;
+                let names: Vec<&str> = regex.capture_names().flatten().collect();
 
-        let output = cmd
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await
-            .context("Failed to execute command")?;
+                let matches: Vec<Value> = regex
+                    .captures_iter(stdout)
+                    .map(|captures| {
+                        // Optional groups that didn't take part are null
+                        names
+                            .iter()
+                            .map(|name| (name.to_string(), json!(captures.name(name).map(|m| m.as_str()))))
+                            .collect::<serde_json::Map<_, _>>()
+                            .into()
+                    })
+                    .collect();
+                Ok(Value::Array(matches))
+            }
+            _ => {
+                // Try to parse as JSON first
+                if let Ok(json_value) = serde_json::from_str::<Value>(stdout) {
+                    Ok(json_value)
+                } else {
+                    Ok(json!({
+                        "output": stdout.trim(),
+                        "status": "success"
+                    }))
+                }
+            }
+        }
+    }
 
-        if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
+    // A failed read fails the call - truncated output would pass for the whole thing
+    async fn read_pipe(pipe: Option<impl AsyncRead + Unpin>) -> std::io::Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut buffer).await?;
+        }
+        Ok(buffer)
+    }
 
-            // Try to parse as JSON first
-            if let Ok(json_value) = serde_json::from_str::<Value>(&stdout) {
-                Ok(json_value)
-            } else {
-                Ok(json!({
-                    "output": stdout.trim(),
-                    "status": "success"
-                }))
+    // SIGTERM first so the command can clean up; SIGKILL if it outlives the grace period.
+    // Returns the signal that stopped it
+    async fn terminate(child: &mut Child, pid: Option<u32>, grace: Duration) -> &'static str {
+        #[cfg(unix)]
+        if let Some(pid) = pid {
+            // The command leads its own group, so -pid reaches everything it started.
+            // Safe: plain kill(2); the group id stays reserved while any member is alive
+            let group = -(pid as libc::pid_t);
+            unsafe {
+                libc::kill(group, libc::SIGTERM);
+            }
+
+            // Done when the whole group is gone, not just the direct child
+            let group_gone = async {
+                loop {
+                    let _ = child.try_wait();
+                    if unsafe { libc::kill(group, 0) } != 0 {
+                        break;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            };
+            if tokio::time::timeout(grace, group_gone).await.is_ok() {
+                return "SIGTERM";
             }
+
+            unsafe {
+                libc::kill(group, libc::SIGKILL);
+            }
+            let _ = child.wait().await;
+            return "SIGKILL";
+        }
+        #[cfg(not(unix))]
+        let _ = (pid, grace);
+
+        let _ = child.kill().await;
+        "SIGKILL"
+    }
+
+    // Dry run - report exactly what execute_tool would do, without doing it
+    pub fn explain_tool(&self, name: &str, args: Value, injected_values: &HashMap<String, String>) -> Result<Value, ToolError> {
+        let tool = self
+            .tools
+            .get(name)
+            .ok_or_else(|| ToolError::NotFound(name.to_string()))?;
+
+        let args = self.drop_unknown_args(tool, args);
+        let masked = Self::env_default_args(tool, &args);
+        let args = self
+            .apply_defaults(tool, args)
+            .map_err(ToolError::invalid_arguments)?;
+        Self::validate_schema(tool, &args)?;
+        self.validate_arguments(tool, &args)
+            .map_err(ToolError::invalid_arguments)?;
+
+        if let Some(handler) = &tool.internal_handler {
+            return Ok(json!({
+                "dry_run": true,
+                "tool": name,
+                "handler": handler,
+                "args": Self::redact_args(args, &masked)
+            }));
+        }
+
+        let argv = self
+            .command_args(tool, &args)
+            .map_err(ToolError::invalid_arguments)?;
+        let argv = self.redact_argv(tool, &args, &masked, &argv);
+
+        // Where execute_command will run it - allowed_root when the tool sets one
+        let working_dir = match &tool.validation.allowed_root {
+            Some(root) => std::fs::canonicalize(root).unwrap_or_else(|_| PathBuf::from(root)),
+            None => std::env::current_dir().unwrap_or_default(),
+        };
+
+        // The tool's env as configured - resolved values may be secrets
+        let mut env = Self::injected_env(injected_values);
+        env.extend(tool.env.clone());
+
+        Ok(json!({
+            "dry_run": true,
+            "tool": name,
+            "command": Self::program(tool),
+            "argv": argv,
+            "env": env,
+            "working_dir": working_dir.display().to_string()
+        }))
+    }
+
+    // Omitted args that will be filled from a ${VAR} default. Their values may be
+    // secrets, so they're masked wherever the call is echoed back or logged
+    fn env_default_args(tool: &ToolDefinition, supplied: &Value) -> Vec<String> {
+        tool.args
+            .iter()
+            .filter(|arg_def| arg_def.default.as_deref().is_some_and(|default| default.contains("${")))
+            .filter(|arg_def| supplied.get(&arg_def.name).is_none())
+            .map(|arg_def| arg_def.name.clone())
+            .collect()
+    }
+
+    fn redact_args(mut args: Value, masked: &[String]) -> Value {
+        for name in masked {
+            if let Some(value) = args.get_mut(name) {
+                *value = json!(REDACTED);
+            }
+        }
+        args
+    }
+
+    // argv with the text of every masked value replaced - covers flag values,
+    // KEY=VALUE pairs and shell scripts alike
+    fn redact_argv(&self, tool: &ToolDefinition, args: &Value, masked: &[String], argv: &[String]) -> Vec<String> {
+        let secrets: Vec<String> = tool
+            .args
+            .iter()
+            .filter(|arg_def| masked.contains(&arg_def.name))
+            .filter_map(|arg_def| args.get(&arg_def.name).map(|value| (arg_def, value)))
+            .flat_map(|(arg_def, value)| self.expand_arg_value(tool, arg_def, value).unwrap_or_default())
+            .filter(|secret| !secret.is_empty())
+            .collect();
+
+        argv.iter()
+            .map(|entry| {
+                secrets
+                    .iter()
+                    .fold(entry.clone(), |entry, secret| entry.replace(secret.as_str(), REDACTED))
+            })
+            .collect()
+    }
+
+    // The tool's own env with ${VAR} references resolved
+    fn tool_env(&self, tool: &ToolDefinition) -> Result<BTreeMap<String, String>> {
+        tool.env
+            .iter()
+            .map(|(key, template)| {
+                let value = expand_env_refs(template, &self.secrets)
+                    .with_context(|| format!("Cannot resolve env '{}' for tool '{}'", key, tool.name))?;
+                Ok((key.clone(), value))
+            })
+            .collect()
+    }
+
+    // Injected values reach commands as GAMECODE_<KEY> environment variables
+    fn injected_env(injected_values: &HashMap<String, String>) -> BTreeMap<String, String> {
+        injected_values
+            .iter()
+            .map(|(key, value)| (format!("GAMECODE_{}", key.to_uppercase()), value.clone()))
+            .collect()
+    }
+
+    // What a validated path argument must pass: no traversal, absolute paths only when
+    // allowed, and the real target under allowed_root when one is set
+    fn check_path(tool: &ToolDefinition, path: &str) -> Result<()> {
+        validation::validate_path(path, tool.validation.allow_absolute_paths)?;
+        if let Some(root) = &tool.validation.allowed_root {
+            validation::validate_path_resolved(path, Path::new(root))?;
+        }
+        Ok(())
+    }
+
+    // Built-in file handlers check their own paths whatever the YAML says: no traversal,
+    // absolute paths only when allowed, and the real target under allowed_root (or the
+    // server's directory when that isn't set)
+    fn handler_path(tool: &ToolDefinition, path: &str) -> Result<PathBuf> {
+        validation::validate_path(path, tool.validation.allow_absolute_paths)?;
+        let root = tool.validation.allowed_root.as_deref().unwrap_or(".");
+        validation::validate_path_resolved(path, Path::new(root))
+    }
+
+    // What gets spawned - shell-mode tools run their command as a script
+    fn program(tool: &ToolDefinition) -> &str {
+        if tool.shell { "sh" } else { &tool.command }
+    }
+
+    fn command_args(&self, tool: &ToolDefinition, args: &Value) -> Result<Vec<String>> {
+        if tool.shell {
+            Ok(vec!["-c".to_string(), self.shell_script(tool, args)?])
         } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow::anyhow!("Command failed: {}", stderr))
+            self.build_argv(tool, args)
+        }
+    }
+
+    // One pass over the template, so a value containing "{name}" is never expanded
+    // again. Braces that don't name a declared arg (awk's '{print}', ${HOME}) are left
+    // alone, and omitted args expand to nothing
+    fn shell_script(&self, tool: &ToolDefinition, args: &Value) -> Result<String> {
+        let mut script = String::new();
+        let mut rest = tool.command.as_str();
+
+        while let Some(start) = rest.find('{') {
+            script.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let arg_def = after.find('}').and_then(|end| {
+                let name = &after[..end];
+                tool.args.iter().find(|arg_def| arg_def.name == name)
+            });
+
+            match arg_def {
+                Some(arg_def) => {
+                    if let Some(value) = args.get(&arg_def.name) {
+                        let words = self.expand_arg_value(tool, arg_def, value)?;
+                        let quoted: Vec<String> = words.iter().map(|word| shell_quote(word)).collect();
+                        script.push_str(&quoted.join(" "));
+                    }
+                    rest = &after[arg_def.name.len() + 1..];
+                }
+                None => {
+                    script.push('{');
+                    rest = after;
+                }
+            }
+        }
+
+        script.push_str(rest);
+        Ok(script)
+    }
+
+    // Argument construction - no shell interpretation, direct args only
+    fn build_argv(&self, tool: &ToolDefinition, args: &Value) -> Result<Vec<String>> {
+        let mut argv: Vec<String> = tool.static_flags.clone();
+        let mut positional = Vec::new();
+        // Explicit positions move every positional arg after the flags, sorted
+        let ordered = tool.args.iter().any(|arg_def| arg_def.position.is_some());
+
+        if let Some(obj) = args.as_object() {
+            for arg_def in &tool.args {
+                if let Some(value) = obj.get(&arg_def.name) {
+                    if arg_def.flag_style.as_deref() == Some("presence") {
+                        if value.as_bool() == Some(true) {
+                            argv.extend(arg_def.cli_flag.clone());
+                        }
+                        continue;
+                    }
+
+                    for arg_value in self.expand_arg_value(tool, arg_def, value)? {
+                        if let Some(cli_flag) = &arg_def.cli_flag {
+                            // Array values repeat the flag: -e a -e b
+                            argv.push(cli_flag.clone());
+                            argv.push(arg_value);
+                        } else if tool.use_arg_separator || ordered {
+                            // Held back so they can be sorted and follow the "--" separator
+                            positional.push((arg_def.position.unwrap_or(usize::MAX), arg_value));
+                        } else {
+                            // Positional argument
+                            argv.push(arg_value);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Stable sort - unpositioned args keep their listed order, after positioned ones
+        positional.sort_by_key(|(position, _)| *position);
+
+        // "--" stops option parsing, so values like "-weird.txt" stay operands
+        if tool.use_arg_separator && !positional.is_empty() {
+            argv.push("--".to_string());
+        }
+        argv.extend(positional.into_iter().map(|(_, value)| value));
+
+        Ok(argv)
+    }
+
+    // One JSON value may become several argv entries - arrays and globs expand
+    fn expand_arg_value(&self, tool: &ToolDefinition, arg_def: &ArgDefinition, value: &Value) -> Result<Vec<String>> {
+        // Objects of a keyvalue arg become one KEY=VALUE entry per pair
+        if arg_def.arg_type == "keyvalue" {
+            let map = value.as_object().ok_or_else(|| {
+                anyhow::anyhow!("Argument '{}' must be an object of key/value pairs", arg_def.name)
+            })?;
+
+            let mut pairs = Vec::new();
+            for (key, item) in map {
+                let item = match item {
+                    Value::String(s) => s.clone(),
+                    Value::Number(n) => number_arg(n),
+                    Value::Bool(_) => item.to_string(),
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "Value for key '{}' must be a string, number or boolean",
+                            key
+                        ));
+                    }
+                };
+                validation::validate_key_value(key, &item)?;
+                pairs.push(format!("{}={}", key, item));
+            }
+
+            return Ok(pairs);
+        }
+
+        let items = match value {
+            Value::Array(items) => items.iter().collect(),
+            other => vec![other],
+        };
+
+        let mut values = Vec::new();
+        for item in items {
+            let text = match item {
+                Value::String(s) => s.clone(),
+                Value::Number(n) => number_arg(n),
+                other => other.to_string(),
+            };
+
+            if arg_def.expand_globs {
+                values.extend(Self::expand_glob(tool, &text)?);
+            } else {
+                values.push(text);
+            }
+        }
+
+        Ok(values)
+    }
+
+    // Glob expansion without a shell. Relative patterns match under allowed_root, where
+    // the command runs, and every match gets the checks a literal path would - a pattern
+    // can't reach a file that naming it directly wouldn't
+    fn expand_glob(tool: &ToolDefinition, pattern: &str) -> Result<Vec<String>> {
+        if !pattern.contains(['*', '?', '[']) {
+            return Ok(vec![pattern.to_string()]);
+        }
+
+        let root = tool.validation.allowed_root.as_deref().map(Path::new);
+        let full_pattern = match root {
+            Some(root) if Path::new(pattern).is_relative() => {
+                format!("{}/{}", glob::Pattern::escape(&root.to_string_lossy()), pattern)
+            }
+            _ => pattern.to_string(),
+        };
+        let checked = tool.validation.validate_paths || root.is_some();
+
+        let mut matches = Vec::new();
+        for entry in glob::glob(&full_pattern).with_context(|| format!("Invalid glob pattern '{}'", pattern))? {
+            let entry = entry?;
+            // Back to the form the command sees from its working directory
+            let entry = match root {
+                Some(root) => entry.strip_prefix(root).map(Path::to_path_buf).unwrap_or(entry),
+                None => entry,
+            };
+            let entry = entry.to_string_lossy().into_owned();
+            if checked {
+                Self::check_path(tool, &entry)
+                    .with_context(|| format!("Glob '{}' matched a rejected path '{}'", pattern, entry))?;
+            }
+            matches.push(entry);
+        }
+
+        // Like a shell without nullglob, an unmatched pattern is passed through as-is
+        if matches.is_empty() {
+            matches.push(pattern.to_string());
+        }
+
+        Ok(matches)
+    }
+
+    // Fill omitted arguments from their declared defaults
+    fn apply_defaults(&self, tool: &ToolDefinition, args: Value) -> Result<Value> {
+        let mut obj = match args {
+            Value::Object(obj) => obj,
+            Value::Null => serde_json::Map::new(),
+            other => return Ok(other),
+        };
+
+        for arg_def in &tool.args {
+            if !obj.contains_key(&arg_def.name)
+                && let Some(default) = self.resolved_default(arg_def)?
+            {
+                // A ${VAR} default may be a secret - name its source, not its value
+                match &arg_def.default {
+                    Some(template) if template.contains("${") => {
+                        debug!("Using default for '{}' from {}", arg_def.name, template)
+                    }
+                    _ => debug!("Using default for '{}': {}", arg_def.name, default),
+                }
+                obj.insert(arg_def.name.clone(), default);
+            }
+        }
+
+        Ok(Value::Object(obj))
+    }
+
+    // Defaults are written as strings in YAML - convert to the declared type.
+    // ${VAR} defaults aren't known until execution, so they're None here and
+    // never show up in the advertised schema.
+    fn typed_default(arg_def: &ArgDefinition) -> Result<Option<Value>> {
+        match &arg_def.default {
+            Some(default) if !default.contains("${") => Self::convert_default(arg_def, default).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    // The value used for an omitted argument, with ${VAR} references resolved now
+    fn resolved_default(&self, arg_def: &ArgDefinition) -> Result<Option<Value>> {
+        let Some(default) = &arg_def.default else {
+            return Ok(None);
+        };
+        if !default.contains("${") {
+            return Self::typed_default(arg_def);
         }
+
+        let expanded = expand_env_refs(default, &self.secrets)
+            .with_context(|| format!("Cannot resolve default for argument '{}'", arg_def.name))?;
+        // Don't echo the resolved value - it came from the deployment, not the caller
+        let value = Self::convert_default(arg_def, &expanded).map_err(|_| {
+            anyhow::anyhow!(
+                "Default for argument '{}' from {} is not a valid {}",
+                arg_def.name,
+                default,
+                arg_def.arg_type
+            )
+        })?;
+
+        Ok(Some(value))
+    }
+
+    fn convert_default(arg_def: &ArgDefinition, default: &str) -> Result<Value> {
+        let value = match arg_def.arg_type.as_str() {
+            "number" => {
+                if let Ok(int) = default.parse::<i64>() {
+                    json!(int)
+                } else if let Ok(int) = default.parse::<u64>() {
+                    json!(int)
+                } else {
+                    let float = default.parse::<f64>().map_err(|_| {
+                        anyhow::anyhow!(
+                            "Default '{}' for argument '{}' is not a valid number",
+                            default,
+                            arg_def.name
+                        )
+                    })?;
+                    json!(float)
+                }
+            }
+            "boolean" => {
+                let flag = default.parse::<bool>().map_err(|_| {
+                    anyhow::anyhow!(
+                        "Default '{}' for argument '{}' is not a valid boolean",
+                        default,
+                        arg_def.name
+                    )
+                })?;
+                json!(flag)
+            }
+            // Arrays may be written as a JSON list or as a single element
+            "array" => serde_json::from_str::<Vec<Value>>(default)
+                .map(Value::Array)
+                .unwrap_or_else(|_| json!([default])),
+            _ => json!(default),
+        };
+
+        Ok(value)
+    }
+
+    // Optional per-argument checks, driven by the tool's validation config
+    fn validate_arguments(&self, tool: &ToolDefinition, args: &Value) -> Result<()> {
+        let Some(obj) = args.as_object() else {
+            return Ok(());
+        };
+
+        for arg_def in &tool.args {
+            if let Some(value) = obj.get(&arg_def.name) {
+                if tool.validation.validate_args {
+                    validation::validate_typed_value(value, &arg_def.arg_type)?;
+                }
+
+                // Values reach a shell script - suspicious patterns are errors here
+                if tool.shell && !tool.allow_shell_metacharacters {
+                    for text in shell_strings(value) {
+                        validation::validate_shell_arg(text)
+                            .with_context(|| format!("Invalid value for argument '{}'", arg_def.name))?;
+                    }
+                }
+
+                // Path validation if marked as path - arrays check every element
+                if arg_def.is_path && tool.validation.validate_paths {
+                    let paths: Vec<&str> = match value {
+                        Value::Array(items) => items.iter().filter_map(|v| v.as_str()).collect(),
+                        other => other.as_str().into_iter().collect(),
+                    };
+
+                    for path_str in paths {
+                        Self::check_path(tool, path_str)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
     }
 
     // Internal handlers - hardcoded, no dynamic evaluation
-    async fn execute_internal_handler(&self, handler: &str, args: &Value, _injected_values: &HashMap<String, String>) -> Result<Value> {
+    async fn execute_internal_handler(&self, tool: &ToolDefinition, handler: &str, args: &Value, _injected_values: &HashMap<String, String>) -> Result<Value> {
         match handler {
             "add" => {
-                let a = args
-                    .get("a")
-                    .and_then(|v| v.as_f64())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'a'"))?;
-                let b = args
-                    .get("b")
-                    .and_then(|v| v.as_f64())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'b'"))?;
+                let a = get_f64(args, "a")?;
+                let b = get_f64(args, "b")?;
                 Ok(json!({
                     "result": a + b,
                     "operation": "addition"
                 }))
             }
             "multiply" => {
-                let a = args
-                    .get("a")
-                    .and_then(|v| v.as_f64())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'a'"))?;
-                let b = args
-                    .get("b")
-                    .and_then(|v| v.as_f64())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'b'"))?;
+                let a = get_f64(args, "a")?;
+                let b = get_f64(args, "b")?;
                 Ok(json!({
                     "result": a * b,
                     "operation": "multiplication"
                 }))
             }
+            // Only exists when a tool opts in - reports where config came from, never values
+            "__describe_config" => {
+                let sources: Vec<String> = self
+                    .sources
+                    .iter()
+                    .map(|source| source.display().to_string())
+                    .collect();
+
+                Ok(json!({
+                    "sources": sources,
+                    "mode": self.mode,
+                    "tool_count": self.tools.len(),
+                    "server_version": env!("CARGO_PKG_VERSION")
+                }))
+            }
             "list_files" => {
-                let path = args.get("path").and_then(|v| v.as_str()).unwrap_or(".");
+                let path = get_opt_str(args, "path")?.unwrap_or(".");
+                let max_depth = if get_opt_bool(args, "recursive")?.unwrap_or(false) {
+                    get_opt_u64(args, "max_depth")?
+                        .unwrap_or(DEFAULT_LIST_DEPTH)
+                        .min(MAX_LIST_DEPTH)
+                } else {
+                    0
+                };
+                let filter = get_opt_str(args, "glob")?
+                    .map(glob::Pattern::new)
+                    .transpose()
+                    .map_err(|e| anyhow::anyhow!("Invalid glob filter: {}", e))?;
+                let sort_by = get_opt_str(args, "sort_by")?.unwrap_or("name");
+                if !["name", "size", "mtime"].contains(&sort_by) {
+                    return Err(anyhow::anyhow!(
+                        "argument 'sort_by' must be one of name, size, mtime, got '{}'",
+                        sort_by
+                    ));
+                }
+
+                let root = &Self::handler_path(tool, path)?;
+
+                // (relative path, size, modified, entry) - sorted once everything is read
+                let mut found = Vec::new();
+                // Unreadable entries are reported rather than failing the whole listing
+                let mut skipped = Vec::new();
+                let mut pending = vec![(root.to_path_buf(), 0)];
+                while let Some((dir, depth)) = pending.pop() {
+                    let relative_to_root = |entry_path: &Path| {
+                        entry_path.strip_prefix(root).unwrap_or(entry_path).to_string_lossy().to_string()
+                    };
+                    let mut entries = match tokio::fs::read_dir(&dir).await {
+                        Ok(entries) => entries,
+                        // The base itself must be listable
+                        Err(e) if depth == 0 => return Err(e.into()),
+                        Err(e) => {
+                            skipped.push(json!({ "path": relative_to_root(&dir), "error": e.to_string() }));
+                            continue;
+                        }
+                    };
+                    loop {
+                        let entry = match entries.next_entry().await {
+                            Ok(Some(entry)) => entry,
+                            Ok(None) => break,
+                            Err(e) => {
+                                skipped.push(json!({ "path": relative_to_root(&dir), "error": e.to_string() }));
+                                break;
+                            }
+                        };
+                        let entry_path = entry.path();
+                        // Doesn't follow symlinks, so a linked directory is listed but never entered
+                        let metadata = match entry.metadata().await {
+                            Ok(metadata) => metadata,
+                            Err(e) => {
+                                skipped.push(json!({ "path": relative_to_root(&entry_path), "error": e.to_string() }));
+                                continue;
+                            }
+                        };
+                        let relative = entry_path.strip_prefix(root).unwrap_or(&entry_path).to_path_buf();
 
-                let mut files = Vec::new();
-                let mut entries = tokio::fs::read_dir(path).await?;
+                        if metadata.is_dir() && depth < max_depth {
+                            pending.push((entry_path.clone(), depth + 1));
+                        }
+                        if let Some(filter) = &filter
+                            && !filter.matches_path(&relative)
+                        {
+                            continue;
+                        }
 
-                while let Some(entry) = entries.next_entry().await? {
-                    let metadata = entry.metadata().await?;
-                    files.push(json!({
-                        "name": entry.file_name().to_string_lossy(),
-                        "is_dir": metadata.is_dir(),
-                        "size": metadata.len()
-                    }));
+                        let modified = metadata
+                            .modified()
+                            .ok()
+                            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                            .map_or(0, |since| since.as_secs());
+                        let relative = relative.to_string_lossy().to_string();
+                        let file = json!({
+                            "name": entry.file_name().to_string_lossy(),
+                            "path": relative,
+                            "is_dir": metadata.is_dir(),
+                            "size": metadata.len(),
+                            "modified": modified
+                        });
+                        found.push((relative, metadata.len(), modified, file));
+                    }
                 }
 
-                Ok(json!({
+                // Names ascending; sizes and times largest/newest first
+                match sort_by {
+                    "size" => found.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0))),
+                    "mtime" => found.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0))),
+                    _ => found.sort_by(|a, b| a.0.cmp(&b.0)),
+                }
+                let files: Vec<Value> = found.into_iter().map(|(_, _, _, file)| file).collect();
+
+                let mut listing = json!({
                     "path": path,
                     "files": files
+                });
+                if !skipped.is_empty() {
+                    listing["skipped"] = Value::Array(skipped);
+                }
+                Ok(listing)
+            }
+            "read_file" | "safe_read_file" => {
+                let path = get_str(args, "path")?;
+
+                // safe_read_file is a compatibility alias for read_file
+                let read_path = Self::handler_path(tool, path)?;
+                let offset = get_opt_u64(args, "offset")?.unwrap_or(0);
+                let length = get_opt_u64(args, "length")?;
+                let max_bytes = tool
+                    .validation
+                    .max_read_bytes
+                    .unwrap_or(DEFAULT_MAX_READ_BYTES);
+
+                // Size check happens before any content is loaded
+                let file_size = tokio::fs::metadata(&read_path).await?.len();
+                let remaining = file_size.saturating_sub(offset);
+                let to_read = length.map_or(remaining, |len| len.min(remaining));
+                if to_read > max_bytes {
+                    return Err(anyhow::anyhow!(
+                        "Read of {} bytes exceeds limit of {} bytes (use offset/length for partial reads)",
+                        to_read,
+                        max_bytes
+                    ));
+                }
+
+                let mut file = tokio::fs::File::open(&read_path).await?;
+                file.seek(std::io::SeekFrom::Start(offset)).await?;
+                let mut buffer = Vec::with_capacity(to_read as usize);
+                file.take(to_read).read_to_end(&mut buffer).await?;
+
+                Ok(json!({
+                    "path": path,
+                    "content": String::from_utf8_lossy(&buffer),
+                    "bytes": buffer.len()
                 }))
             }
             "write_file" => {
-                let path = args
-                    .get("path")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'path'"))?;
-                let content = args
-                    .get("content")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'content'"))?;
+                let path = get_str(args, "path")?;
+                let content = get_str(args, "content")?;
 
-                tokio::fs::write(path, content).await?;
+                tokio::fs::write(Self::handler_path(tool, path)?, content).await?;
 
                 Ok(json!({
                     "status": "success",
@@ -429,61 +1908,59 @@ impl ToolManager {
                 }))
             }
             "create_graphviz_diagram" => {
-                let filename = args
-                    .get("filename")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'filename'"))?;
-                let format = args
-                    .get("format")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'format'"))?;
-                let content = args
-                    .get("content")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'content'"))?;
+                let filename = get_str(args, "filename")?;
+                let format = diagram_format(args, GRAPHVIZ_FORMATS)?;
+                let content = get_str(args, "content")?;
+
+                let dir = Self::diagram_dir(tool, args, filename).await?;
 
                 // Save DOT source file
-                let dot_file = format!("{}.dot", filename);
+                let dot_file = dir.join(format!("{}.dot", filename));
                 tokio::fs::write(&dot_file, content).await?;
 
                 // Generate diagram using GraphViz
-                let output_file = format!("{}.{}", filename, format);
+                let output_file = dir.join(format!("{}.{}", filename, format));
                 let output = tokio::process::Command::new("dot")
                     .arg(format!("-T{}", format))
                     .arg(&dot_file)
                     .arg("-o")
                     .arg(&output_file)
                     .output()
-                    .await?;
+                    .await
+                    .map_err(|e| {
+                        if e.kind() == std::io::ErrorKind::NotFound {
+                            anyhow::anyhow!("GraphViz 'dot' binary not found on PATH; install graphviz")
+                        } else {
+                            e.into()
+                        }
+                    })?;
 
                 if !output.status.success() {
                     let stderr = String::from_utf8_lossy(&output.stderr);
                     return Err(anyhow::anyhow!("GraphViz error: {}", stderr));
                 }
 
-                Ok(json!({
+                let mut result = json!({
                     "status": "success",
                     "source_file": dot_file,
                     "output_file": output_file,
                     "format": format
-                }))
+                });
+                if get_opt_bool(args, "embed_resource")?.unwrap_or(false) {
+                    result[RESOURCE_KEY] = Self::embedded_resource(&output_file, format).await?;
+                }
+
+                Ok(result)
             }
             "create_plantuml_diagram" => {
-                let filename = args
-                    .get("filename")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'filename'"))?;
-                let format = args
-                    .get("format")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'format'"))?;
-                let content = args
-                    .get("content")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| anyhow::anyhow!("Missing parameter 'content'"))?;
+                let filename = get_str(args, "filename")?;
+                let format = diagram_format(args, PLANTUML_FORMATS)?;
+                let content = get_str(args, "content")?;
+
+                let dir = Self::diagram_dir(tool, args, filename).await?;
 
                 // Save PlantUML source file
-                let puml_file = format!("{}.puml", filename);
+                let puml_file = dir.join(format!("{}.puml", filename));
                 tokio::fs::write(&puml_file, content).await?;
 
                 // Generate diagram using PlantUML
@@ -491,24 +1968,629 @@ impl ToolManager {
                     .arg(format!("-t{}", format))
                     .arg(&puml_file)
                     .output()
-                    .await?;
+                    .await
+                    .map_err(|e| {
+                        if e.kind() == std::io::ErrorKind::NotFound {
+                            anyhow::anyhow!("PlantUML 'plantuml' binary not found on PATH; install plantuml")
+                        } else {
+                            e.into()
+                        }
+                    })?;
 
                 if !output.status.success() {
                     let stderr = String::from_utf8_lossy(&output.stderr);
                     return Err(anyhow::anyhow!("PlantUML error: {}", stderr));
                 }
 
-                // PlantUML generates output with same base name
-                let output_file = format!("{}.{}", filename, format);
+                // PlantUML generates output with same base name, next to the source
+                let output_file = dir.join(format!("{}.{}", filename, format));
 
-                Ok(json!({
+                let mut result = json!({
                     "status": "success",
                     "source_file": puml_file,
                     "output_file": output_file,
                     "format": format
-                }))
+                });
+                if get_opt_bool(args, "embed_resource")?.unwrap_or(false) {
+                    result[RESOURCE_KEY] = Self::embedded_resource(&output_file, format).await?;
+                }
+
+                Ok(result)
             }
+            "git" => Self::run_git(tool, args).await,
             _ => Err(anyhow::anyhow!("Unknown internal handler: {}", handler)),
         }
     }
+
+    // Whitelisted, read-only git. The argv is assembled here from checked pieces, so the
+    // caller can't smuggle in options. Config that makes these subcommands run programs
+    // is switched off: fsmonitor, the pager, external diff, textconv, signature checks,
+    // and every configured filter driver .gitattributes could point at
+    async fn run_git(tool: &ToolDefinition, args: &Value) -> Result<Value> {
+        let subcommand = get_str(args, "subcommand")?;
+        let allowed_flags = GIT_SUBCOMMANDS
+            .iter()
+            .find(|(name, _)| *name == subcommand)
+            .map(|(_, flags)| *flags)
+            .ok_or_else(|| {
+                let names: Vec<&str> = GIT_SUBCOMMANDS.iter().map(|(name, _)| *name).collect();
+                anyhow::anyhow!(
+                    "git subcommand '{}' is not allowed (allowed: {})",
+                    subcommand,
+                    names.join(", ")
+                )
+            })?;
+
+        // Pinned to allowed_root when the tool sets one, otherwise the server's directory
+        let repo = tool.validation.allowed_root.as_deref().unwrap_or(".");
+
+        let mut argv: Vec<String> = [
+            "--no-pager",
+            "-c",
+            "core.fsmonitor=false",
+            "-c",
+            "log.showSignature=false",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        argv.extend(Self::git_filter_overrides(repo).await?);
+        argv.push(subcommand.to_string());
+        if subcommand == "diff" || subcommand == "show" {
+            argv.push("--no-ext-diff".to_string());
+            argv.push("--no-textconv".to_string());
+        }
+
+        for flag in get_opt_str_array(args, "flags")? {
+            if !allowed_flags.contains(&flag) {
+                return Err(anyhow::anyhow!(
+                    "git {} does not allow flag '{}' (allowed: {})",
+                    subcommand,
+                    flag,
+                    allowed_flags.join(", ")
+                ));
+            }
+            argv.push(flag.to_string());
+        }
+
+        if let Some(max_count) = get_opt_u64(args, "max_count")? {
+            if subcommand != "log" {
+                return Err(anyhow::anyhow!("max_count only applies to git log"));
+            }
+            argv.push(format!("--max-count={}", max_count));
+        }
+
+        if let Some(revision) = get_opt_str(args, "revision")? {
+            if subcommand == "status" {
+                return Err(anyhow::anyhow!("git status does not take a revision"));
+            }
+            validation::validate_git_revision(revision)?;
+            argv.push(revision.to_string());
+        }
+
+        // Paths always follow "--", so none of them can be read as an option or revision
+        argv.push("--".to_string());
+        for path in get_opt_str_array(args, "paths")? {
+            validation::validate_path(path, false)?;
+            argv.push(path.to_string());
+        }
+
+        debug!("Running git in {}: {:?}", repo, argv);
+        let output = Command::new("git")
+            .args(&argv)
+            .current_dir(repo)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .await
+            .map_err(git_spawn_error)?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("git {} failed: {}", subcommand, stderr.trim()));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(json!({
+            "subcommand": subcommand,
+            "argv": argv,
+            "lines": stdout.lines().collect::<Vec<_>>()
+        }))
+    }
+
+    // Empty commands for every filter driver the repo's config defines - git then passes
+    // content through unfiltered. Listing config runs nothing
+    async fn git_filter_overrides(repo: &str) -> Result<Vec<String>> {
+        let output = Command::new("git")
+            .args(["config", "--get-regexp", r"^filter\."])
+            .current_dir(repo)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .await
+            .map_err(git_spawn_error)?;
+
+        // Exits 1 when nothing matches
+        let listing = String::from_utf8_lossy(&output.stdout);
+        let mut drivers: Vec<&str> = listing
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .filter_map(|key| key.strip_prefix("filter.")?.rsplit_once('.'))
+            .map(|(driver, _)| driver)
+            .collect();
+        drivers.sort_unstable();
+        drivers.dedup();
+
+        Ok(drivers
+            .into_iter()
+            .flat_map(|driver| {
+                ["clean=", "smudge=", "process=", "required=false"]
+                    .map(|setting| ["-c".to_string(), format!("filter.{}.{}", driver, setting)])
+            })
+            .flatten()
+            .collect())
+    }
+
+    // MCP embedded resource for a generated file - SVG as text, everything else base64
+    async fn embedded_resource(path: &Path, format: &str) -> Result<Value> {
+        let bytes = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read generated file '{}'", path.display()))?;
+        let uri = format!("file://{}", path.display());
+
+        Ok(match format {
+            "svg" => json!({
+                "uri": uri,
+                "mimeType": "image/svg+xml",
+                "text": String::from_utf8_lossy(&bytes)
+            }),
+            _ => {
+                let mime_type = match format {
+                    "png" => "image/png",
+                    "jpg" | "jpeg" => "image/jpeg",
+                    "pdf" => "application/pdf",
+                    _ => "application/octet-stream",
+                };
+                json!({
+                    "uri": uri,
+                    "mimeType": mime_type,
+                    "blob": BASE64_STANDARD.encode(bytes)
+                })
+            }
+        })
+    }
+
+    // Diagram files go in output_dir (or the working directory) under a bare filename
+    async fn diagram_dir(tool: &ToolDefinition, args: &Value, filename: &str) -> Result<PathBuf> {
+        if filename.is_empty() || filename == "." || filename == ".." || filename.contains(['/', '\\']) {
+            return Err(anyhow::anyhow!(
+                "Diagram filename '{}' must not contain path separators",
+                filename
+            ));
+        }
+
+        let dir = get_opt_str(args, "output_dir")?.unwrap_or(".");
+        validation::validate_path(dir, tool.validation.allow_absolute_paths)?;
+
+        // Missing directories are created below the deepest one that exists, which must
+        // resolve inside the root first - so a symlink can't carry the new ones outside
+        let root = Path::new(tool.validation.allowed_root.as_deref().unwrap_or("."));
+        let target = root.join(dir);
+        let mut existing = target.as_path();
+        while existing.symlink_metadata().is_err() {
+            existing = existing
+                .parent()
+                .with_context(|| format!("Cannot resolve output directory '{}'", dir))?;
+        }
+        let existing = std::path::absolute(existing)?;
+        validation::validate_path_resolved(&existing.to_string_lossy(), root)?;
+        tokio::fs::create_dir_all(&target)
+            .await
+            .with_context(|| format!("Failed to create output directory '{}'", dir))?;
+
+        // Absolute paths in the result - the caller may not share our working directory
+        Self::handler_path(tool, dir)
+    }
+}
+
+// Replace each ${NAME} with that secret, or else that environment variable - unset
+// variables are an error
+fn expand_env_refs(template: &str, secrets: &HashMap<String, String>) -> Result<String> {
+    let mut expanded = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| anyhow::anyhow!("Unclosed '${{' in '{}'", template))?;
+        let name = &after[..end];
+        let value = match secrets.get(name) {
+            Some(secret) => secret.clone(),
+            None => std::env::var(name)
+                .map_err(|_| anyhow::anyhow!("Environment variable '{}' is not set", name))?,
+        };
+        expanded.push_str(&value);
+        rest = &after[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+// Cut a result down to `limit` characters, marked with "truncated" and the original
+// length. Plain command output is cut as text; any other result as its JSON, which
+// becomes the "output". Extra content blocks, where the tool may emit them, are left alone
+fn truncate_result(name: &str, mut result: Value, limit: usize, blocks: bool) -> Value {
+    let mut extra = serde_json::Map::new();
+    if blocks && let Some(fields) = result.as_object_mut() {
+        for key in [RESOURCE_KEY, CONTENT_KEY] {
+            if let Some(value) = fields.remove(key) {
+                extra.insert(key.to_string(), value);
+            }
+        }
+    }
+
+    let plain = result.as_object().is_some_and(|fields| fields.len() == 2 && fields.contains_key("status"));
+    let text = match result.get("output").and_then(Value::as_str) {
+        Some(output) if plain => output.to_string(),
+        _ => result.to_string(),
+    };
+
+    let length = text.chars().count();
+    if length > limit {
+        info!("Truncated result of '{}' from {} to {} characters", name, length, limit);
+        let prefix: String = text.chars().take(limit).collect();
+        if plain {
+            result["output"] = json!(prefix);
+            result["truncated"] = json!(true);
+            result["original_length"] = json!(length);
+        } else {
+            result = json!({ "output": prefix, "truncated": true, "original_length": length });
+        }
+    }
+
+    // Extra blocks only come from an object result, which stays an object
+    if let Some(fields) = result.as_object_mut() {
+        fields.extend(extra);
+    }
+    result
+}
+
+// Single-quoted for sh - nothing inside is special except the quote itself
+fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+// Every string that could reach a shell-mode script: the value itself, array items,
+// and keyvalue keys and values
+fn shell_strings(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(s) => vec![s.as_str()],
+        Value::Array(items) => items.iter().flat_map(shell_strings).collect(),
+        Value::Object(map) => map
+            .iter()
+            .flat_map(|(key, item)| std::iter::once(key.as_str()).chain(shell_strings(item)))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// KEY=VALUE per line; blank lines and # comments are skipped, "export " is allowed
+// and one pair of surrounding quotes is removed from the value
+fn parse_secrets(content: &str) -> Result<HashMap<String, String>> {
+    let mut secrets = HashMap::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+
+        // Report the line number, never the line - it may hold a secret
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Line {} is not KEY=VALUE", index + 1))?;
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(anyhow::anyhow!("Line {} has an invalid key", index + 1));
+        }
+
+        let value = value.trim();
+        let value = [('"', '"'), ('\'', '\'')]
+            .iter()
+            .find_map(|(open, close)| value.strip_prefix(*open)?.strip_suffix(*close))
+            .unwrap_or(value);
+        secrets.insert(key.to_string(), value.to_string());
+    }
+
+    Ok(secrets)
+}
+
+// One step of an output_path
+#[derive(Debug)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+// Minimal JSONPath subset: $, .key, ['key'] and [index] - no wildcards or filters
+fn parse_output_path(path: &str) -> Result<Vec<PathSegment>> {
+    let rest = path.strip_prefix('$').unwrap_or(path);
+    let mut chars = rest.chars().peekable();
+    let mut segments = Vec::new();
+
+    // A bare "data.items" is accepted as shorthand for "$.data.items"
+    if !rest.is_empty() && !rest.starts_with(['.', '[']) {
+        let key: String = std::iter::from_fn(|| chars.next_if(|c| *c != '.' && *c != '[')).collect();
+        segments.push(PathSegment::Key(key));
+    }
+
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let key: String = std::iter::from_fn(|| chars.next_if(|c| *c != '.' && *c != '[')).collect();
+                if key.is_empty() {
+                    return Err(anyhow::anyhow!("Empty key in output path '{}'", path));
+                }
+                segments.push(PathSegment::Key(key));
+            }
+            '[' => {
+                let inner: String = std::iter::from_fn(|| chars.next_if(|c| *c != ']')).collect();
+                if chars.next() != Some(']') {
+                    return Err(anyhow::anyhow!("Unclosed '[' in output path '{}'", path));
+                }
+
+                let quoted = inner
+                    .strip_prefix('\'')
+                    .and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+                if let Some(key) = quoted {
+                    segments.push(PathSegment::Key(key.to_string()));
+                } else {
+                    let index = inner.trim().parse::<usize>().map_err(|_| {
+                        anyhow::anyhow!("Invalid index '{}' in output path '{}'", inner, path)
+                    })?;
+                    segments.push(PathSegment::Index(index));
+                }
+            }
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Unexpected '{}' in output path '{}'",
+                    other,
+                    path
+                ));
+            }
+        }
+    }
+
+    Ok(segments)
+}
+
+fn extract_output_path(value: &Value, path: &str) -> Result<Value> {
+    let mut current = value;
+    for segment in parse_output_path(path)? {
+        let next = match &segment {
+            PathSegment::Key(key) => current.get(key.as_str()),
+            PathSegment::Index(index) => current.get(*index),
+        };
+        current = next.ok_or_else(|| {
+            anyhow::anyhow!("output_path '{}' did not match the tool output", path)
+        })?;
+    }
+
+    Ok(current.clone())
+}
+
+// The config format, generated from the structs the loader deserializes into so the
+// two can't drift. Tools may also name a template with `extends`, which can supply
+// the description. Every object is closed so unknown keys show up - the loader
+// ignores them, so validate_config_schema reports those as warnings
+fn config_schema() -> Value {
+    let mut tool = schemars::schema_for!(ToolDefinition).to_value();
+    let defs = tool.as_object_mut().and_then(|fields| fields.remove("$defs"));
+    tool["properties"]["extends"] = json!({ "type": "string" });
+    tool["properties"]["internal_handler"] = json!({ "enum": BUILTIN_HANDLERS });
+    tool["required"] = json!(["name"]);
+    tool["if"] = json!({ "not": { "required": ["extends"] } });
+    tool["then"] = json!({ "required": ["name", "description"] });
+
+    let mut config = schemars::schema_for!(ToolsConfig).to_value();
+    config["properties"]["tools"]["items"] = tool;
+    if let Some(defs) = defs {
+        config["$defs"] = defs;
+    }
+    close_objects(&mut config);
+    config
+}
+
+// additionalProperties: false on every schema that lists properties
+fn close_objects(schema: &mut Value) {
+    match schema {
+        Value::Object(fields) => {
+            if fields.contains_key("properties") && !fields.contains_key("additionalProperties") {
+                fields.insert("additionalProperties".to_string(), json!(false));
+            }
+            fields.values_mut().for_each(close_objects);
+        }
+        Value::Array(items) => items.iter_mut().for_each(close_objects),
+        _ => {}
+    }
+}
+
+// Numbers as argv text: integers digit for digit, floats in plain decimal.
+// serde_json's own formatting switches to exponents (1e21), which commands misread
+fn number_arg(number: &serde_json::Number) -> String {
+    if let Some(int) = number.as_u64() {
+        int.to_string()
+    } else if let Some(int) = number.as_i64() {
+        int.to_string()
+    } else {
+        number.as_f64().map_or_else(|| number.to_string(), |float| float.to_string())
+    }
+}
+
+// 1-based line of `name: <name>` (YAML) or `"name": "<name>"` (JSON)
+fn tool_line(content: &str, name: &str) -> Option<usize> {
+    content.lines().position(|line| {
+        let line = line.trim().trim_start_matches("- ");
+        let value = line
+            .strip_prefix("name:")
+            .or_else(|| line.strip_prefix("\"name\":"))
+            .map(|value| value.trim().trim_end_matches(',').trim_matches(['"', '\'']));
+        value == Some(name)
+    }).map(|index| index + 1)
+}
+
+fn diagram_format<'a>(args: &'a Value, supported: &[&str]) -> Result<&'a str> {
+    let format = get_str(args, "format")?;
+    if !supported.contains(&format) {
+        return Err(anyhow::anyhow!(
+            "argument 'format' must be one of {}, got '{}'",
+            supported.join(", "),
+            format
+        ));
+    }
+    Ok(format)
+}
+
+fn git_spawn_error(e: std::io::Error) -> anyhow::Error {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        anyhow::anyhow!("'git' binary not found on PATH")
+    } else {
+        anyhow::Error::new(e).context("Failed to run git")
+    }
+}
+
+// Typed argument access for internal handlers, so every handler words its errors the same way
+fn get_required<'a>(args: &'a Value, name: &str) -> Result<&'a Value> {
+    match args.get(name) {
+        Some(Value::Null) | None => Err(anyhow::anyhow!("Missing parameter '{}'", name)),
+        Some(value) => Ok(value),
+    }
+}
+
+fn get_str<'a>(args: &'a Value, name: &str) -> Result<&'a str> {
+    let value = get_required(args, name)?;
+    value.as_str().ok_or_else(|| wrong_type(name, "a string", value))
+}
+
+fn get_f64(args: &Value, name: &str) -> Result<f64> {
+    let value = get_required(args, name)?;
+    value.as_f64().ok_or_else(|| wrong_type(name, "a number", value))
+}
+
+// Optional variants: absent or null is None, present with the wrong type is still an error
+fn get_opt_str<'a>(args: &'a Value, name: &str) -> Result<Option<&'a str>> {
+    match args.get(name) {
+        Some(Value::Null) | None => Ok(None),
+        Some(_) => get_str(args, name).map(Some),
+    }
+}
+
+fn get_opt_str_array<'a>(args: &'a Value, name: &str) -> Result<Vec<&'a str>> {
+    match args.get(name) {
+        Some(Value::Null) | None => Ok(Vec::new()),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| item.as_str().ok_or_else(|| wrong_type(name, "an array of strings", item)))
+            .collect(),
+        Some(value) => Err(wrong_type(name, "an array of strings", value)),
+    }
+}
+
+fn get_opt_u64(args: &Value, name: &str) -> Result<Option<u64>> {
+    match args.get(name) {
+        Some(Value::Null) | None => Ok(None),
+        Some(value) => value
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| wrong_type(name, "a non-negative integer", value)),
+    }
+}
+
+fn get_opt_bool(args: &Value, name: &str) -> Result<Option<bool>> {
+    match args.get(name) {
+        Some(Value::Null) | None => Ok(None),
+        Some(value) => value
+            .as_bool()
+            .map(Some)
+            .ok_or_else(|| wrong_type(name, "a boolean", value)),
+    }
+}
+
+fn wrong_type(name: &str, expected: &str, value: &Value) -> anyhow::Error {
+    let actual = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    anyhow::anyhow!("argument '{}' must be {}, got {}", name, expected, actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_argument_extraction() {
+        let args = json!({ "a": 2.5, "name": "x", "count": 3, "flag": true, "empty": null });
+
+        assert_eq!(get_f64(&args, "a").unwrap(), 2.5);
+        assert_eq!(get_str(&args, "name").unwrap(), "x");
+        assert_eq!(get_opt_u64(&args, "count").unwrap(), Some(3));
+        assert_eq!(get_opt_bool(&args, "flag").unwrap(), Some(true));
+
+        // Absent and null are the same to optional lookups
+        assert_eq!(get_opt_str(&args, "missing").unwrap(), None);
+        assert_eq!(get_opt_str(&args, "empty").unwrap(), None);
+    }
+
+    #[test]
+    fn test_argument_extraction_errors() {
+        let args = json!({ "a": "2", "b": 1.5, "flag": "yes", "empty": null });
+
+        assert_eq!(get_f64(&args, "a").unwrap_err().to_string(), "argument 'a' must be a number, got string");
+        assert_eq!(get_str(&args, "b").unwrap_err().to_string(), "argument 'b' must be a string, got number");
+        assert_eq!(get_str(&args, "missing").unwrap_err().to_string(), "Missing parameter 'missing'");
+        assert_eq!(get_required(&args, "empty").unwrap_err().to_string(), "Missing parameter 'empty'");
+        assert_eq!(
+            get_opt_u64(&args, "b").unwrap_err().to_string(),
+            "argument 'b' must be a non-negative integer, got number"
+        );
+        assert_eq!(get_opt_bool(&args, "flag").unwrap_err().to_string(), "argument 'flag' must be a boolean, got string");
+    }
+
+    // Hands out one chunk, then fails like a broken pipe
+    struct FailingPipe {
+        sent: bool,
+    }
+
+    impl AsyncRead for FailingPipe {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            if self.sent {
+                return std::task::Poll::Ready(Err(std::io::Error::other("pipe broke")));
+            }
+            self.sent = true;
+            buf.put_slice(b"partial");
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_pipe_reports_errors() {
+        let err = ToolManager::read_pipe(Some(FailingPipe { sent: false })).await.unwrap_err();
+        assert_eq!(err.to_string(), "pipe broke");
+
+        let output = ToolManager::read_pipe(Some(&b"complete"[..])).await.unwrap();
+        assert_eq!(output, b"complete");
+        assert!(ToolManager::read_pipe(None::<&[u8]>).await.unwrap().is_empty());
+    }
 }
@@ -1,8 +1,9 @@
 // Input validation for LLM-provided arguments
 // Minimal approach - document what we check and why
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use serde_json::Value;
+use std::path::{Path, PathBuf};
 
 // Validate file paths - prevent directory traversal
 pub fn validate_path(path: &str, allow_absolute: bool) -> Result<()> {
@@ -24,6 +25,53 @@ pub fn validate_path(path: &str, allow_absolute: bool) -> Result<()> {
     Ok(())
 }
 
+// Resolve symlinks and require the real target to stay under an allowed root.
+// Lexical checks can't see where a symlink points - this one can.
+// Relative paths are relative to the root, never the server's working directory
+pub fn validate_path_resolved(path: &str, root: &Path) -> Result<PathBuf> {
+    if path.contains('\0') {
+        bail!("Path contains null byte");
+    }
+
+    let root = root
+        .canonicalize()
+        .with_context(|| format!("Cannot resolve allowed root '{}'", root.display()))?;
+    // Joining an absolute path replaces the root, so those are checked as given
+    let candidate = root.join(path);
+
+    let resolved = match candidate.canonicalize() {
+        Ok(resolved) => resolved,
+        Err(_) => {
+            // A link whose target doesn't exist would be followed on write
+            if candidate.symlink_metadata().is_ok() {
+                bail!("Path is a dangling symlink: '{}'", path);
+            }
+
+            // New files don't exist yet - resolve the parent directory instead
+            let file_name = candidate
+                .file_name()
+                .with_context(|| format!("Path has no file name: '{}'", path))?;
+            let parent = candidate
+                .parent()
+                .with_context(|| format!("Path has no parent directory: '{}'", path))?;
+            parent
+                .canonicalize()
+                .with_context(|| format!("Cannot resolve parent directory of '{}'", path))?
+                .join(file_name)
+        }
+    };
+
+    if !resolved.starts_with(&root) {
+        bail!(
+            "Path escapes allowed root: '{}' resolves outside '{}'",
+            path,
+            root.display()
+        );
+    }
+
+    Ok(resolved)
+}
+
 // Validate command arguments for common injection patterns
 pub fn validate_command_arg(arg: &str) -> Result<()> {
     // Reject null bytes
@@ -31,6 +79,28 @@ pub fn validate_command_arg(arg: &str) -> Result<()> {
         bail!("Argument contains null byte");
     }
     
+    if let Some(pattern) = shell_metacharacter(arg) {
+        // Log but don't reject - these are safe without shell
+        tracing::warn!("Suspicious pattern '{}' in argument: {}", pattern, arg);
+    }
+    
+    Ok(())
+}
+
+// Shell-mode tools: the patterns validate_command_arg only logs are errors here
+pub fn validate_shell_arg(arg: &str) -> Result<()> {
+    if arg.contains('\0') {
+        bail!("Argument contains null byte");
+    }
+    if let Some(pattern) = shell_metacharacter(arg) {
+        bail!("Shell metacharacter '{}' is not allowed in arguments to a shell-mode tool", pattern.escape_default());
+    }
+
+    Ok(())
+}
+
+// The first suspicious pattern in the argument, if any
+fn shell_metacharacter(arg: &str) -> Option<&'static str> {
     // These would only be dangerous with shell interpretation,
     // but checking them adds defense in depth
     const SUSPICIOUS_PATTERNS: &[&str] = &[
@@ -47,13 +117,43 @@ pub fn validate_command_arg(arg: &str) -> Result<()> {
         "\r",      // Carriage return
     ];
     
-    for pattern in SUSPICIOUS_PATTERNS {
-        if arg.contains(pattern) {
-            // Log but don't reject - these are safe without shell
-            tracing::warn!("Suspicious pattern '{}' in argument: {}", pattern, arg);
-        }
+    SUSPICIOUS_PATTERNS.iter().copied().find(|pattern| arg.contains(pattern))
+}
+
+// Validate one KEY=VALUE pair - the key must not be able to change the pair's shape
+pub fn validate_key_value(key: &str, value: &str) -> Result<()> {
+    if key.is_empty() {
+        bail!("Key must not be empty");
     }
-    
+    if key.contains('=') {
+        bail!("Key '{}' must not contain '='", key);
+    }
+    if key.starts_with('-') {
+        bail!("Key '{}' must not start with '-'", key);
+    }
+    if key.chars().any(|c| c.is_whitespace() || c == '\0') {
+        bail!("Key '{}' contains whitespace or a null byte", key);
+    }
+
+    validate_command_arg(value)
+}
+
+// Validate a git revision (branch, tag, sha, HEAD~2, main..feature) - it must not be
+// readable as an option, and only characters found in ref names and rev syntax pass
+pub fn validate_git_revision(revision: &str) -> Result<()> {
+    if revision.is_empty() {
+        bail!("Revision must not be empty");
+    }
+    if revision.starts_with('-') {
+        bail!("Revision '{}' must not start with '-'", revision);
+    }
+    if let Some(c) = revision
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || "._/~^@{}-".contains(*c)))
+    {
+        bail!("Revision '{}' contains disallowed character '{}'", revision, c);
+    }
+
     Ok(())
 }
 
@@ -69,6 +169,15 @@ pub fn validate_typed_value(value: &Value, expected_type: &str) -> Result<()> {
         ("boolean", Value::Bool(_)) => {
             // Booleans are safe
         }
+        ("keyvalue", Value::Object(map)) => {
+            for (key, item) in map {
+                match item {
+                    Value::String(s) => validate_key_value(key, s)?,
+                    Value::Number(_) | Value::Bool(_) => validate_key_value(key, "")?,
+                    _ => bail!("Value for key '{}' must be a string, number or boolean", key),
+                }
+            }
+        }
         ("array", Value::Array(arr)) => {
             // Validate each element
             for item in arr {
@@ -123,5 +232,11 @@ mod tests {
         
         // Should fail
         assert!(validate_command_arg("test\0null").is_err());
+
+        // Shell-mode tools reject what is only logged otherwise
+        assert!(validate_shell_arg("hello world").is_ok());
+        assert!(validate_shell_arg("test; ls").is_err());
+        assert!(validate_shell_arg("a | b").is_err());
+        assert!(validate_shell_arg("$(whoami)").is_err());
     }
 }
\ No newline at end of file
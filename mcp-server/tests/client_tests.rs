@@ -0,0 +1,219 @@
+// The mcp-client crate against the real server binary
+
+use mcp_client::McpClient;
+use mcp_client::blocking::BlockingMcpClient;
+use mcp_client::reconnecting::ReconnectingMcpClient;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_blocking_client_against_server() {
+    let args = vec![
+        "--tools-file".to_string(),
+        "tests/fixtures/test_tools.yaml".to_string(),
+    ];
+    let mut client = BlockingMcpClient::connect(env!("CARGO_BIN_EXE_gamecode-mcp2"), &args).unwrap();
+
+    let init = client.initialize("blocking-test", "1.0.0").unwrap();
+    assert_eq!(init.server_info.name, "gamecode-mcp2");
+
+    let tools = client.list_tools().unwrap();
+    assert!(tools.iter().any(|t| t.name == "math_add"));
+
+    let result = client.call_tool("math_add", json!({ "a": 2, "b": 3 })).unwrap();
+    assert_eq!(result["result"], 5.0);
+}
+
+#[tokio::test]
+async fn test_client_keeps_image_content() {
+    let args = vec![
+        "--tools-file".to_string(),
+        "tests/fixtures/output_tools.yaml".to_string(),
+    ];
+    let mut client = McpClient::connect(env!("CARGO_BIN_EXE_gamecode-mcp2"), &args)
+        .await
+        .unwrap();
+    client.initialize("image-test", "1.0.0").await.unwrap();
+
+    let result = client.call_tool_full("image_result", json!({})).await.unwrap();
+
+    // JSON stays in the text block, the image arrives as its own block
+    assert_eq!(result.content.len(), 2);
+    assert_eq!(result.json().unwrap(), json!({ "status": "rendered" }));
+    let images: Vec<(&str, &str)> = result.images().collect();
+    assert_eq!(images, vec![("iVBORw0KGgo=", "image/png")]);
+}
+
+#[tokio::test]
+async fn test_client_batches_tool_calls() {
+    let args = vec![
+        "--tools-file".to_string(),
+        "tests/fixtures/test_tools.yaml".to_string(),
+    ];
+    let mut client = McpClient::connect(env!("CARGO_BIN_EXE_gamecode-mcp2"), &args)
+        .await
+        .unwrap();
+    client.initialize("batch-test", "1.0.0").await.unwrap();
+    let first_id = client.current_request_id();
+
+    let results = client
+        .call_tools_batch(vec![
+            ("math_add".to_string(), json!({ "a": 2, "b": 3 })),
+            ("list_dir".to_string(), json!({ "path": "tests/fixtures" })),
+            ("no_such_tool".to_string(), json!({})),
+        ])
+        .await
+        .unwrap();
+
+    // One round trip for all three
+    assert_eq!(client.current_request_id(), first_id + 1);
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap().json().unwrap()["result"], 5.0);
+    let listing = results[1].as_ref().unwrap().json().unwrap();
+    assert!(listing.to_string().contains("test_tools.yaml"));
+    assert!(results[2].as_ref().unwrap_err().to_string().contains("not found"));
+}
+
+#[tokio::test]
+async fn test_client_request_timeout() {
+    // A "server" that reads nothing and never answers
+    let client = McpClient::connect("sleep", &["5".to_string()]).await.unwrap();
+    let mut client = client.with_timeout(Duration::from_millis(200));
+
+    let started = Instant::now();
+    let err = client.list_tools().await.unwrap_err();
+    assert_eq!(err.to_string(), "MCP request 'tools/list' timed out");
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    // The stream may now be out of step, so later requests refuse to run
+    let err = client.list_tools().await.unwrap_err();
+    assert!(err.to_string().contains("unusable after a timed-out request"));
+}
+
+#[tokio::test]
+async fn test_request_ids_continue_across_reconnect() {
+    let args = vec![
+        "--tools-file".to_string(),
+        "tests/fixtures/test_tools.yaml".to_string(),
+    ];
+    let server = env!("CARGO_BIN_EXE_gamecode-mcp2");
+    let mut ids = Vec::new();
+
+    let mut client = McpClient::connect(server, &args).await.unwrap();
+    assert_eq!(client.current_request_id(), 0);
+    client.initialize("id-test", "1.0.0").await.unwrap();
+    ids.push(client.current_request_id());
+    client.list_tools().await.unwrap();
+    ids.push(client.current_request_id());
+    let last = client.current_request_id();
+    drop(client);
+
+    // Simulated reconnect: a fresh connection picks up where the old one stopped
+    let mut client = McpClient::connect(server, &args)
+        .await
+        .unwrap()
+        .with_request_ids_after(last);
+    client.initialize("id-test", "1.0.0").await.unwrap();
+    ids.push(client.current_request_id());
+    client.list_tools().await.unwrap();
+    ids.push(client.current_request_id());
+
+    assert_eq!(ids, vec![1, 2, 3, 4]);
+}
+
+#[tokio::test]
+async fn test_client_reports_server_exit() {
+    let args = vec!["-c".to_string(), "echo 'config missing' >&2; exit 3".to_string()];
+    let mut client = McpClient::connect("sh", &args).await.unwrap();
+
+    // Whether the write or the read notices first, the error names the exit
+    let err = client.initialize("exit-test", "1.0.0").await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "MCP server process exited (exit status: 3); stderr: config missing"
+    );
+}
+
+#[tokio::test]
+async fn test_reconnecting_client_survives_server_death() {
+    let args = vec![
+        "--tools-file".to_string(),
+        "tests/fixtures/test_tools.yaml".to_string(),
+    ];
+    let mut client = ReconnectingMcpClient::connect(
+        env!("CARGO_BIN_EXE_gamecode-mcp2"),
+        &args,
+        "reconnect-test",
+        "1.0.0",
+    )
+    .await
+    .unwrap()
+    .with_retry_delay(Duration::from_millis(10));
+
+    let result = client.call_tool("math_add", json!({ "a": 1, "b": 2 })).await.unwrap();
+    assert_eq!(result["result"], 3.0);
+
+    let first_pid = client.server_pid().unwrap();
+    let status = std::process::Command::new("kill")
+        .args(["-9", &first_pid.to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // The next call notices the dead server, respawns it and retries
+    let result = client.call_tool("math_add", json!({ "a": 2, "b": 3 })).await.unwrap();
+    assert_eq!(result["result"], 5.0);
+    assert_ne!(client.server_pid().unwrap(), first_pid);
+
+    // Tool errors from a healthy server are not treated as a lost connection
+    let pid = client.server_pid().unwrap();
+    assert!(client.call_tool("no_such_tool", json!({})).await.is_err());
+    assert_eq!(client.server_pid().unwrap(), pid);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_reconnect_after_timeout_stops_old_server() {
+    let args = vec![
+        "--tools-file".to_string(),
+        "tests/fixtures/timeout_tools.yaml".to_string(),
+    ];
+    let mut client = ReconnectingMcpClient::connect(
+        env!("CARGO_BIN_EXE_gamecode-mcp2"),
+        &args,
+        "reconnect-test",
+        "1.0.0",
+    )
+    .await
+    .unwrap()
+    .with_timeout(Duration::from_secs(1))
+    .with_retry_delay(Duration::from_millis(10));
+
+    // The request times out while the server is still busy with it
+    let first_pid = client.server_pid().unwrap();
+    let err = client.call_tool("slow", json!({})).await.unwrap_err();
+    assert!(err.to_string().contains("timed out"), "{}", err);
+
+    client.ping().await.unwrap();
+    assert_ne!(client.server_pid().unwrap(), first_pid);
+    assert!(!std::path::Path::new(&format!("/proc/{}", first_pid)).exists());
+}
+
+#[tokio::test]
+async fn test_client_read_only_session() {
+    let args = vec![
+        "--tools-file".to_string(),
+        "tests/fixtures/test_tools.yaml".to_string(),
+    ];
+    let mut client = McpClient::connect(env!("CARGO_BIN_EXE_gamecode-mcp2"), &args).await.unwrap();
+    client.initialize_read_only("read-only-test", "1.0.0").await.unwrap();
+
+    let mut names: Vec<String> = client.list_tools().await.unwrap().into_iter().map(|t| t.name).collect();
+    names.sort();
+    assert_eq!(names, ["list_dir", "math_add"]);
+
+    let result = client.call_tool("math_add", json!({ "a": 2, "b": 3 })).await.unwrap();
+    assert_eq!(result["result"], 5.0);
+    let err = client.call_tool("echo_test", json!({ "message": "hi" })).await.unwrap_err();
+    assert!(err.to_string().contains("read-only"), "{}", err);
+}
@@ -0,0 +1,67 @@
+// Diagram handlers against a stand-in renderer. This file is its own test
+// binary, so putting a fake `dot` on PATH can't affect other tests.
+// The fake is a shell script, so the whole file is Unix-only.
+#![cfg(unix)]
+
+use gamecode_mcp2::handlers::RequestHandler;
+use gamecode_mcp2::protocol::*;
+use gamecode_mcp2::tools::ToolManager;
+use serde_json::json;
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn test_diagram_returns_embedded_resource() {
+    // Fake `dot`: called as `dot -Tsvg <source> -o <output>`
+    let bin_dir = TempDir::new().unwrap();
+    let dot = bin_dir.path().join("dot");
+    std::fs::write(&dot, "#!/bin/sh\nprintf '<svg>fake</svg>' > \"$4\"\n").unwrap();
+    std::fs::set_permissions(&dot, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin_dir.path().display(), std::env::var("PATH").unwrap());
+    // Safety: the only test in this binary, set before any process is spawned
+    unsafe { std::env::set_var("PATH", path) };
+
+    let mut tool_manager = ToolManager::new();
+    let config = PathBuf::from("tests/fixtures/diagram_tools.yaml");
+    tool_manager.load_from_file(&config).await.unwrap();
+    let handler = RequestHandler::new(tool_manager, HashMap::new());
+
+    let output_dir = TempDir::new_in(".").unwrap();
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(1),
+        method: "tools/call".to_string(),
+        params: Some(json!({
+            "name": "graphviz_diagram",
+            "arguments": {
+                "filename": "flow",
+                "content": "digraph { a -> b }",
+                "output_dir": output_dir.path().to_str().unwrap(),
+                "embed_resource": true
+            }
+        })),
+    };
+
+    let response = handler.handle_request(request).await;
+    assert!(response.error.is_none(), "Call failed: {:?}", response.error);
+    let result: CallToolResult = serde_json::from_value(response.result.unwrap()).unwrap();
+    assert_eq!(result.content.len(), 2);
+
+    // The JSON summary stays in the text block, without the resource key
+    let ContentBlock::Text { text } = &result.content[0] else {
+        panic!("First block should be text");
+    };
+    let summary: serde_json::Value = serde_json::from_str(text).unwrap();
+    let output_file = summary["output_file"].as_str().unwrap();
+    assert!(summary.get("_resource").is_none());
+
+    let ContentBlock::Resource { resource } = &result.content[1] else {
+        panic!("Second block should be an embedded resource");
+    };
+    assert_eq!(resource.uri, format!("file://{}", output_file));
+    assert_eq!(resource.mime_type.as_deref(), Some("image/svg+xml"));
+    assert_eq!(resource.text.as_deref(), Some("<svg>fake</svg>"));
+    assert!(resource.blob.is_none());
+}
@@ -167,17 +167,116 @@ async fn test_tool_call_nonexistent_tool() {
 
     let response = handler.handle_request(request).await;
 
-    assert!(
-        response.error.is_none(),
-        "Should return error in content, not JSON-RPC error"
-    );
-    assert!(response.result.is_some());
+    // Unknown tools are a protocol error, distinguishable by data.reason
+    assert!(response.result.is_none());
+    let error = response.error.expect("Should return a JSON-RPC error");
+    assert_eq!(error.code, INVALID_PARAMS);
+    assert!(error.message.contains("not found"));
+    assert_eq!(error.data.unwrap()["reason"], "tool_not_found");
+}
+
+#[tokio::test]
+async fn test_tool_call_invalid_arguments() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/read_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let handler = RequestHandler::new(tool_manager, HashMap::new());
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(10),
+        method: "tools/call".to_string(),
+        params: Some(json!({
+            "name": "file_reader",
+            "arguments": { "path": "../../etc/passwd" }
+        })),
+    };
+
+    let response = handler.handle_request(request).await;
 
+    assert!(response.result.is_none());
+    let error = response.error.expect("Should return a JSON-RPC error");
+    assert_eq!(error.code, INVALID_PARAMS);
+    assert!(error.message.contains("Path traversal detected"));
+    assert_eq!(error.data.unwrap()["reason"], "invalid_arguments");
+}
+
+#[tokio::test]
+async fn test_tool_call_schema_type_mismatch() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let handler = RequestHandler::new(tool_manager, HashMap::new());
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(11),
+        method: "tools/call".to_string(),
+        params: Some(json!({
+            "name": "print_count",
+            "arguments": { "count": "many" }
+        })),
+    };
+
+    let response = handler.handle_request(request).await;
+
+    assert!(response.result.is_none());
+    let error = response.error.expect("Should return a JSON-RPC error");
+    assert_eq!(error.code, INVALID_PARAMS);
+    let data = error.data.unwrap();
+    assert_eq!(data["reason"], "invalid_arguments");
+    let violations = data["violations"].as_array().unwrap();
+    assert_eq!(violations.len(), 1);
+    assert!(violations[0].as_str().unwrap().starts_with("/count:"));
+}
+
+#[tokio::test]
+async fn test_tool_call_schema_unknown_property() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let handler = RequestHandler::new(tool_manager, HashMap::new());
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(12),
+        method: "tools/call".to_string(),
+        params: Some(json!({
+            "name": "print_count",
+            "arguments": { "count": 3, "verbose": true }
+        })),
+    };
+
+    let response = handler.handle_request(request).await;
+
+    assert!(response.result.is_none());
+    let error = response.error.expect("Should return a JSON-RPC error");
+    assert_eq!(error.code, INVALID_PARAMS);
+    assert!(error.message.contains("verbose"));
+    assert_eq!(error.data.unwrap()["violations"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_tool_call_execution_failure_stays_in_content() {
+    let handler = setup_handler().await;
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(11),
+        method: "tools/call".to_string(),
+        params: Some(json!({
+            "name": "list_dir",
+            "arguments": { "path": "/definitely/not/a/real/dir" }
+        })),
+    };
+
+    let response = handler.handle_request(request).await;
+
+    // The tool ran and failed - the LLM sees that as an error result
+    assert!(response.error.is_none());
     let result = response.result.unwrap();
     assert_eq!(result["isError"], true);
-
-    let content = &result["content"][0];
-    assert!(content["text"].as_str().unwrap().contains("not found"));
+    assert!(result["content"][0]["text"].as_str().unwrap().starts_with("Error:"));
 }
 
 #[tokio::test]
@@ -193,3 +292,608 @@ async fn test_notification_handling() {
     // Notifications don't return responses, just verify no panic
     handler.handle_notification(notification).await;
 }
+
+#[tokio::test]
+async fn test_tools_call_dry_run() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let mut injected = HashMap::new();
+    injected.insert("tenant".to_string(), "acme".to_string());
+    let handler = RequestHandler::new(tool_manager, injected);
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let target = temp_dir.path().join("created.txt");
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(7),
+        method: "tools/call".to_string(),
+        params: Some(json!({
+            "name": "touch_file",
+            "arguments": {
+                "path": target.to_str().unwrap()
+            },
+            "_meta": {
+                "dryRun": true
+            }
+        })),
+    };
+
+    let response = handler.handle_request(request).await;
+    assert!(response.error.is_none(), "Dry run failed: {:?}", response.error);
+
+    let result = response.result.unwrap();
+    assert!(result["isError"].is_null());
+    let text = result["content"][0]["text"].as_str().unwrap();
+    let plan: serde_json::Value = serde_json::from_str(text).unwrap();
+
+    assert_eq!(plan["dry_run"], true);
+    assert_eq!(plan["command"], "touch");
+    assert_eq!(plan["argv"], json!([target.to_str().unwrap()]));
+    assert_eq!(plan["env"]["GAMECODE_TENANT"], "acme");
+    let cwd = std::env::current_dir().unwrap();
+    assert_eq!(plan["working_dir"], cwd.display().to_string());
+
+    // Nothing was executed
+    assert!(!target.exists(), "Dry run must not run the command");
+}
+
+#[tokio::test]
+async fn test_dry_run_reports_allowed_root_as_working_dir() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config = temp_dir.path().join("tools.yaml");
+    std::fs::write(
+        &config,
+        format!(
+            r#"
+tools:
+  - name: rooted_ls
+    description: List under a fixed root
+    command: ls
+    validation:
+      allowed_root: "{}"
+"#,
+            temp_dir.path().display()
+        ),
+    )
+    .unwrap();
+    let mut tool_manager = ToolManager::new();
+    tool_manager.load_from_file(&config).await.unwrap();
+
+    // Commands run in the root, so that's the directory the plan names
+    let plan = tool_manager.explain_tool("rooted_ls", json!({}), &HashMap::new()).unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    assert_eq!(plan["working_dir"], root.display().to_string());
+}
+
+#[tokio::test]
+async fn test_tools_call_dry_run_internal_handler() {
+    let handler = setup_handler().await;
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(8),
+        method: "tools/call".to_string(),
+        params: Some(json!({
+            "name": "list_dir",
+            "arguments": {},
+            "_meta": { "dryRun": true }
+        })),
+    };
+
+    let response = handler.handle_request(request).await;
+    let result = response.result.unwrap();
+    let text = result["content"][0]["text"].as_str().unwrap();
+    let plan: serde_json::Value = serde_json::from_str(text).unwrap();
+
+    assert_eq!(plan["handler"], "list_files");
+    // Resolved args include the declared default
+    assert_eq!(plan["args"]["path"], ".");
+}
+
+#[tokio::test]
+async fn test_initialize_reports_empty_config() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/empty_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let handler = RequestHandler::new(tool_manager, HashMap::new());
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(9),
+        method: "initialize".to_string(),
+        params: Some(json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": { "name": "test-client", "version": "1.0.0" }
+        })),
+    };
+
+    let response = handler.handle_request(request).await;
+    let result = response.result.unwrap();
+    let instructions = result["instructions"].as_str().unwrap();
+    assert!(instructions.contains("defines no tools"), "{}", instructions);
+}
+
+#[tokio::test]
+async fn test_tools_list_with_tag_filter() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/tagged_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let handler = RequestHandler::new(tool_manager, HashMap::new());
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(12),
+        method: "tools/list".to_string(),
+        params: Some(json!({ "tags": ["files"] })),
+    };
+
+    let response = handler.handle_request(request).await;
+    let result = response.result.unwrap();
+    let tools = result["tools"].as_array().unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0]["name"], "list_dir");
+    assert_eq!(tools[0]["_meta"]["tags"], json!(["files", "read-only"]));
+
+    // Without params every tool is listed
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(13),
+        method: "tools/list".to_string(),
+        params: None,
+    };
+    let response = handler.handle_request(request).await;
+    assert_eq!(response.result.unwrap()["tools"].as_array().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_ping_before_initialize() {
+    let handler = setup_handler().await;
+
+    // No initialize has been sent - ping must still answer
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(20),
+        method: "ping".to_string(),
+        params: None,
+    };
+
+    let response = handler.handle_request(request).await;
+
+    assert!(response.error.is_none(), "Ping failed: {:?}", response.error);
+    let result: PingResult = serde_json::from_value(response.result.unwrap()).unwrap();
+    assert!(result.ok);
+    assert!(result.uptime_ms < 60_000);
+}
+
+#[tokio::test]
+async fn test_second_initialize_rejected() {
+    let handler = setup_handler().await;
+
+    let initialize = |id| JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(id),
+        method: "initialize".to_string(),
+        params: Some(json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": { "tools": {} },
+            "clientInfo": { "name": "test-client", "version": "1.0.0" }
+        })),
+    };
+
+    let first = handler.handle_request(initialize(21)).await;
+    assert!(first.error.is_none(), "Initialize failed: {:?}", first.error);
+
+    let second = handler.handle_request(initialize(22)).await;
+    assert!(second.result.is_none());
+    let error = second.error.expect("Second initialize should be rejected");
+    assert_eq!(error.code, INVALID_REQUEST);
+    assert_eq!(error.data.unwrap()["reason"], "already_initialized");
+
+    // The session is still usable after the rejected handshake
+    let list = handler
+        .handle_request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: json!(23),
+            method: "tools/list".to_string(),
+            params: None,
+        })
+        .await;
+    assert!(list.error.is_none());
+}
+
+#[tokio::test]
+async fn test_client_requested_read_only_session() {
+    let handler = setup_handler().await;
+
+    let init = handler
+        .handle_request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: json!(24),
+            method: "initialize".to_string(),
+            params: Some(json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": { "tools": {}, "read_only": true },
+                "clientInfo": { "name": "test-client", "version": "1.0.0" }
+            })),
+        })
+        .await;
+    assert!(init.error.is_none(), "Initialize failed: {:?}", init.error);
+
+    // Only read_only tools are listed
+    let list = handler
+        .handle_request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: json!(25),
+            method: "tools/list".to_string(),
+            params: None,
+        })
+        .await;
+    let mut names: Vec<String> = list.result.unwrap()["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["list_dir", "math_add"]);
+
+    // A mutating tool is refused before it runs
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let target = temp_dir.path().join("should_not_exist.txt");
+    let call = |id, name: &str, arguments| JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(id),
+        method: "tools/call".to_string(),
+        params: Some(json!({ "name": name, "arguments": arguments })),
+    };
+    let response = handler
+        .handle_request(call(26, "file_writer", json!({ "path": target, "content": "x" })))
+        .await;
+    let result = response.result.unwrap();
+    assert_eq!(result["isError"], true);
+    assert!(result["content"][0]["text"].as_str().unwrap().contains("initialized read-only"));
+    assert!(!target.exists());
+
+    let response = handler.handle_request(call(27, "math_add", json!({ "a": 1, "b": 2 }))).await;
+    assert!(response.result.unwrap().get("isError").is_none());
+}
+
+#[test]
+fn test_oversized_line_rejected_and_loop_continues() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut server = Command::new(env!("CARGO_BIN_EXE_gamecode-mcp2"))
+        .args(["--tools-file", "tests/fixtures/test_tools.yaml", "--max-line-bytes", "1024"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let oversized = format!(
+        r#"{{"jsonrpc":"2.0","id":1,"method":"ping","params":{{"pad":"{}"}}}}"#,
+        "x".repeat(64 * 1024)
+    );
+    {
+        let mut stdin = server.stdin.take().unwrap();
+        writeln!(stdin, "{}", oversized).unwrap();
+        writeln!(stdin, r#"{{"jsonrpc":"2.0","id":2,"method":"ping"}}"#).unwrap();
+    }
+
+    let output = server.wait_with_output().unwrap();
+    let responses: Vec<JsonRpcResponse> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(responses.len(), 2);
+
+    let error = responses[0].error.as_ref().expect("Oversized line should be an error");
+    assert_eq!(error.code, PARSE_ERROR);
+    assert_eq!(error.data.as_ref().unwrap()["reason"], "line_too_long");
+
+    // The next request on the same stream is served normally
+    assert_eq!(responses[1].id, json!(2));
+    assert_eq!(responses[1].result.as_ref().unwrap()["ok"], true);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_secrets_never_echoed_or_logged() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let secret = "sk-live-7f3a9c";
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let secrets = temp_dir.path().join("secrets.env");
+    std::fs::write(&secrets, format!("SECRET_KEY={}\n", secret)).unwrap();
+    let config = temp_dir.path().join("tools.yaml");
+    std::fs::write(
+        &config,
+        r#"
+tools:
+  - name: call_api
+    description: Call an API with a deployment token
+    command: "true"
+    echo_call: true
+    env:
+      API_KEY: "${SECRET_KEY}"
+    args:
+      - name: token
+        description: API token
+        required: false
+        type: string
+        cli_flag: --token
+        default: "${SECRET_KEY}"
+"#,
+    )
+    .unwrap();
+
+    let mut server = Command::new(env!("CARGO_BIN_EXE_gamecode-mcp2"))
+        .arg("--tools-file")
+        .arg(&config)
+        .arg("--secrets-file")
+        .arg(&secrets)
+        .env("RUST_LOG", "debug")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    {
+        let mut stdin = server.stdin.take().unwrap();
+        writeln!(stdin, r#"{{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{{"name":"call_api","arguments":{{}}}}}}"#).unwrap();
+        writeln!(stdin, r#"{{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{{"name":"call_api","arguments":{{}},"_meta":{{"dryRun":true}}}}}}"#).unwrap();
+    }
+    let output = server.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    // Echo envelope, dry-run argv, default and command logs all mask it
+    let responses: Vec<JsonRpcResponse> = stdout.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(responses.len(), 2);
+    let text = |response: &JsonRpcResponse| -> serde_json::Value {
+        let result = response.result.as_ref().unwrap();
+        serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap()
+    };
+    assert_eq!(text(&responses[0])["params"]["token"], "***");
+    assert_eq!(text(&responses[1])["argv"], json!(["--token", "***"]));
+    assert!(stderr.contains("Executing command: true"), "{}", stderr);
+    assert!(!stdout.contains(secret), "{}", stdout);
+    assert!(!stderr.contains(secret), "{}", stderr);
+}
+
+#[test]
+fn test_log_format_selection() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let run = |format: &str| {
+        let mut server = Command::new(env!("CARGO_BIN_EXE_gamecode-mcp2"))
+            .args(["--tools-file", "tests/fixtures/test_tools.yaml", "--log-format", format])
+            .env("RUST_LOG", "info")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        writeln!(server.stdin.take().unwrap(), r#"{{"jsonrpc":"2.0","id":1,"method":"ping"}}"#).unwrap();
+        let output = server.wait_with_output().unwrap();
+
+        // Logs never leak into the protocol stream
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(stdout.lines().count(), 1);
+        String::from_utf8(output.stderr).unwrap()
+    };
+
+    let json_logs = run("json");
+    assert!(!json_logs.is_empty());
+    for line in json_logs.lines() {
+        let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert!(entry["level"].is_string());
+    }
+
+    let human_logs = run("human");
+    assert!(human_logs.contains("Starting GameCode MCP Server"));
+    assert!(serde_json::from_str::<serde_json::Value>(human_logs.lines().next().unwrap()).is_err());
+}
+
+#[tokio::test]
+async fn test_tools_call_batch() {
+    let handler = setup_handler().await;
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(1),
+        method: "tools/call_batch".to_string(),
+        params: Some(json!({
+            "calls": [
+                { "name": "math_add", "arguments": { "a": 2, "b": 3 } },
+                { "name": "no_such_tool", "arguments": {} },
+                { "name": "list_dir", "arguments": { "path": "tests/fixtures" } }
+            ],
+            "maxConcurrency": 4
+        })),
+    };
+    let response = handler.handle_request(request).await;
+    assert!(response.error.is_none(), "{:?}", response.error);
+
+    // Call order, whatever order they finished in
+    let results = response.result.unwrap()["results"].as_array().unwrap().clone();
+    assert_eq!(results.len(), 3);
+    let sum: serde_json::Value =
+        serde_json::from_str(results[0]["content"][0]["text"].as_str().unwrap()).unwrap();
+    assert_eq!(sum["result"], 5.0);
+
+    // A failed call is an entry of its own, not a failed batch
+    assert_eq!(results[1]["error"]["code"], INVALID_PARAMS);
+    assert_eq!(results[1]["error"]["data"]["reason"], "tool_not_found");
+    assert!(results[2]["content"][0]["text"].as_str().unwrap().contains("test_tools.yaml"));
+
+    let calls: Vec<_> = (0..33)
+        .map(|_| json!({ "name": "math_add", "arguments": { "a": 1, "b": 1 } }))
+        .collect();
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(2),
+        method: "tools/call_batch".to_string(),
+        params: Some(json!({ "calls": calls })),
+    };
+    let error = handler.handle_request(request).await.error.unwrap();
+    assert_eq!(error.code, INVALID_PARAMS);
+    assert_eq!(error.data.unwrap()["reason"], "batch_too_large");
+}
+
+#[tokio::test]
+async fn test_invalid_arguments_reference_tool_docs() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/constrained_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let tools = tool_manager.get_mcp_tools();
+    assert_eq!(tools[0].meta.as_ref().unwrap()["docs"], "docs/tools/render.md");
+
+    let handler = RequestHandler::new(tool_manager, HashMap::new());
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(30),
+        method: "tools/call".to_string(),
+        params: Some(json!({
+            "name": "render",
+            "arguments": { "format": "gif" }
+        })),
+    };
+
+    let response = handler.handle_request(request).await;
+
+    let error = response.error.expect("Should return a JSON-RPC error");
+    assert_eq!(error.code, INVALID_PARAMS);
+    assert!(error.message.ends_with("(see docs/tools/render.md)"), "{}", error.message);
+    assert_eq!(error.data.unwrap()["docs"], "docs/tools/render.md");
+}
+
+#[tokio::test]
+async fn test_tool_returns_multiple_blocks() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/output_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let handler = RequestHandler::new(tool_manager, HashMap::new());
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(41),
+        method: "tools/call".to_string(),
+        params: Some(json!({ "name": "multi_block", "arguments": {} })),
+    };
+
+    let response = handler.handle_request(request).await;
+
+    // Just the tool's blocks, in order - no "{}" text block in front
+    let result = response.result.unwrap();
+    assert_eq!(
+        result["content"],
+        json!([
+            { "type": "text", "text": "a.txt\nb.txt" },
+            { "type": "text", "text": "2 files" }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_command_output_blocks_stay_text() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/output_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let handler = RequestHandler::new(tool_manager, HashMap::new());
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(42),
+        method: "tools/call".to_string(),
+        params: Some(json!({ "name": "untrusted_blocks", "arguments": {} })),
+    };
+
+    let response = handler.handle_request(request).await;
+
+    // A command that didn't opt in can't produce an image block - its JSON is text
+    let content = response.result.unwrap()["content"].as_array().unwrap().clone();
+    assert_eq!(content.len(), 1);
+    assert_eq!(content[0]["type"], "text");
+    let text: serde_json::Value = serde_json::from_str(content[0]["text"].as_str().unwrap()).unwrap();
+    assert_eq!(text["_content"][0]["type"], "image");
+}
+
+#[tokio::test]
+async fn test_rpc_shaped_tool_output_stays_content() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/output_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let handler = RequestHandler::new(tool_manager, HashMap::new());
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(40),
+        method: "tools/call".to_string(),
+        params: Some(json!({ "name": "rpc_lookalike", "arguments": {} })),
+    };
+
+    let response = handler.handle_request(request).await;
+
+    // The envelope is ours - same id, a normal tool result
+    assert_eq!(response.id, json!(40));
+    let result = response.result.unwrap();
+    assert!(result.get("hijacked").is_none());
+    assert_eq!(result["content"].as_array().unwrap().len(), 1);
+
+    // The look-alike survives only as an escaped string inside the text block,
+    // and even parsed on its own it isn't a frame
+    let text = result["content"][0]["text"].as_str().unwrap();
+    let inner: serde_json::Value = serde_json::from_str(text).unwrap();
+    assert!(inner.get("jsonrpc").is_none());
+    assert!(serde_json::from_value::<JsonRpcResponse>(inner.clone()).is_err());
+    assert_eq!(inner["output"]["id"], 99);
+
+    // And the wire form is still a single line
+    let line = serde_json::to_string(&result).unwrap();
+    assert!(!line.contains('\n'));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_timeout_escalates_to_sigkill() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/timeout_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let handler = RequestHandler::new(tool_manager, HashMap::new());
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let pid_file = temp_dir.path().join("stubborn.pid");
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(42),
+        method: "tools/call".to_string(),
+        params: Some(json!({ "name": "stubborn", "arguments": { "pid_file": pid_file } })),
+    };
+    let response = handler.handle_request(request).await;
+
+    // The shell ignores SIGTERM, so the grace period runs out and SIGKILL follows
+    let result = response.result.unwrap();
+    assert_eq!(result["isError"], true);
+    assert_eq!(result["_meta"], json!({ "reason": "timeout", "signal": "SIGKILL" }));
+    assert!(result["content"][0]["text"].as_str().unwrap().contains("timed out after 300ms"));
+
+    // And it really is gone
+    let pid = std::fs::read_to_string(&pid_file).unwrap();
+    let alive = std::process::Command::new("kill")
+        .args(["-0", pid.trim()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert!(!alive.success());
+}
+
@@ -185,3 +185,75 @@ async fn test_symlink_traversal() {
         }
     }
 }
+
+#[tokio::test]
+async fn test_symlink_escape_rejected_with_allowed_root() {
+    let root_dir = TempDir::new().unwrap();
+    let outside_dir = TempDir::new().unwrap();
+    let outside_target = outside_dir.path().join("secret.txt");
+    tokio::fs::write(&outside_target, "original").await.unwrap();
+
+    let tools_yaml = root_dir.path().join("tools.yaml");
+    tokio::fs::write(
+        &tools_yaml,
+        format!(
+            r#"
+tools:
+  - name: rooted_writer
+    description: Write files under a fixed root
+    command: internal
+    internal_handler: write_file
+    validation:
+      validate_paths: true
+      allow_absolute_paths: true
+      allowed_root: "{}"
+    args:
+      - name: path
+        description: Output file path
+        required: true
+        type: string
+        is_path: true
+      - name: content
+        description: Content to write
+        required: true
+        type: string
+"#,
+            root_dir.path().display()
+        ),
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    tool_manager.load_from_file(&tools_yaml).await.unwrap();
+
+    // New files inside the root are fine even though they don't exist yet
+    let inside = root_dir.path().join("new.txt");
+    let args = json!({
+        "path": inside.to_str().unwrap(),
+        "content": "hello"
+    });
+    let result = tool_manager.execute_tool("rooted_writer", args, &HashMap::new()).await;
+    assert!(result.is_ok(), "Write inside root failed: {:?}", result);
+
+    #[cfg(unix)]
+    {
+        // A symlink inside the root pointing outside it must be rejected
+        let symlink = root_dir.path().join("escape.txt");
+        std::os::unix::fs::symlink(&outside_target, &symlink).unwrap();
+
+        let args = json!({
+            "path": symlink.to_str().unwrap(),
+            "content": "overwritten"
+        });
+        let result = tool_manager.execute_tool("rooted_writer", args, &HashMap::new()).await;
+        assert!(result.is_err(), "Symlink escape should be rejected");
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("escapes allowed root"));
+
+        let content = tokio::fs::read_to_string(&outside_target).await.unwrap();
+        assert_eq!(content, "original", "Target outside root must be untouched");
+    }
+}
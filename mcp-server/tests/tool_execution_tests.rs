@@ -1,4 +1,4 @@
-use gamecode_mcp2::tools::ToolManager;
+use gamecode_mcp2::tools::{ToolError, ToolManager};
 use serde_json::json;
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -42,7 +42,10 @@ async fn test_execute_internal_math() {
 
 #[tokio::test]
 async fn test_execute_file_operations() {
-    let temp_dir = TempDir::new().unwrap();
+    // File handlers stay under the server's directory when no allowed_root is set,
+    // and relative paths are the default
+    let temp_dir = TempDir::new_in(".").unwrap();
+    let dir_name = temp_dir.path().file_name().unwrap().to_str().unwrap();
     let test_file = temp_dir.path().join("test.txt");
 
     let mut tool_manager = ToolManager::new();
@@ -51,7 +54,7 @@ async fn test_execute_file_operations() {
 
     // Test file writing
     let write_args = json!({
-        "path": test_file.to_str().unwrap(),
+        "path": format!("{}/test.txt", dir_name),
         "content": "Test content"
     });
 
@@ -68,7 +71,7 @@ async fn test_execute_file_operations() {
 
     // Test directory listing
     let list_args = json!({
-        "path": temp_dir.path().to_str().unwrap()
+        "path": dir_name
     });
 
     let list_result = tool_manager.execute_tool("list_dir", list_args, &HashMap::new()).await;
@@ -105,17 +108,17 @@ async fn test_execute_missing_required_args() {
 
     // Try to execute echo_test without required message parameter
     let args = json!({});
+    let error = tool_manager
+        .execute_tool("echo_test", args, &HashMap::new())
+        .await
+        .unwrap_err();
 
-    // The current implementation might not validate required args
-    // This test documents the current behavior
-    let result = tool_manager.execute_tool("echo_test", args, &HashMap::new()).await;
-
-    // If validation is added later, this test should be updated
-    // For now, it likely succeeds but with empty output
-    if result.is_ok() {
-        let output = result.unwrap();
-        // Echo with no args should produce minimal output
-        assert!(output["output"].as_str().is_some());
+    // Rejected by schema validation before anything runs
+    match &error {
+        ToolError::SchemaViolations(violations) => {
+            assert!(violations.iter().any(|v| v.contains("message")), "{:?}", violations);
+        }
+        other => panic!("Expected schema violations, got {:?}", other),
     }
 }
 
@@ -140,3 +143,1364 @@ async fn test_command_injection_prevention() {
         .unwrap()
         .contains("test; rm -rf /tmp/test"));
 }
+
+#[tokio::test]
+async fn test_read_file_handler() {
+    // read_file only reads under the server's directory when no allowed_root is set
+    let temp_dir = TempDir::new_in(".").unwrap();
+    let test_file = temp_dir.path().join("notes.md");
+    tokio::fs::write(&test_file, "# Notes\nfirst line\n").await.unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/read_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let args = json!({
+        "path": test_file.to_str().unwrap()
+    });
+
+    let result = tool_manager.execute_tool("file_reader", args, &HashMap::new()).await;
+    assert!(result.is_ok(), "File read failed: {:?}", result);
+
+    let output = result.unwrap();
+    assert_eq!(output["content"], "# Notes\nfirst line\n");
+    assert_eq!(output["bytes"], 19);
+    assert_eq!(output["path"], test_file.to_str().unwrap());
+}
+
+#[tokio::test]
+async fn test_read_file_partial_read() {
+    let temp_dir = TempDir::new_in(".").unwrap();
+    let test_file = temp_dir.path().join("data.txt");
+    tokio::fs::write(&test_file, "0123456789").await.unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/read_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let args = json!({
+        "path": test_file.to_str().unwrap(),
+        "offset": 3,
+        "length": 4
+    });
+
+    let output = tool_manager
+        .execute_tool("file_reader", args, &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(output["content"], "3456");
+    assert_eq!(output["bytes"], 4);
+}
+
+#[tokio::test]
+async fn test_read_file_size_limit() {
+    let temp_dir = TempDir::new_in(".").unwrap();
+    let test_file = temp_dir.path().join("large.txt");
+    tokio::fs::write(&test_file, "A".repeat(64)).await.unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/read_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // Whole-file read is over the 16 byte limit
+    let args = json!({
+        "path": test_file.to_str().unwrap()
+    });
+    let result = tool_manager.execute_tool("small_file_reader", args, &HashMap::new()).await;
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("exceeds limit"));
+
+    // A partial read within the limit still works
+    let args = json!({
+        "path": test_file.to_str().unwrap(),
+        "length": 8
+    });
+    let result = tool_manager.execute_tool("small_file_reader", args, &HashMap::new()).await;
+    assert!(result.is_ok(), "Partial read failed: {:?}", result);
+    assert_eq!(result.unwrap()["bytes"], 8);
+}
+
+#[tokio::test]
+async fn test_arg_separator_before_positional() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let args = json!({
+        "file": "-weird.txt",
+        "mode": "fast"
+    });
+
+    // Without the separator the dash-leading value is passed straight through
+    let output = tool_manager
+        .execute_tool("print_args", args.clone(), &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+    assert_eq!(lines, vec!["-weird.txt", "--mode", "fast"]);
+
+    // With the separator flags come first, then "--", then positional values
+    let output = tool_manager
+        .execute_tool("print_args_separated", args, &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+    assert_eq!(lines, vec!["--mode", "fast", "--", "-weird.txt"]);
+}
+
+#[tokio::test]
+async fn test_positional_args_follow_position() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // dst is listed before src, but src has the lower position
+    let args = json!({
+        "dst": "backup.txt",
+        "mode": "fast",
+        "src": "notes.txt"
+    });
+
+    let output = tool_manager
+        .execute_tool("print_positioned", args, &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+    assert_eq!(lines, vec!["--mode", "fast", "notes.txt", "backup.txt"]);
+}
+
+#[tokio::test]
+async fn test_presence_flag_only_when_true() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let output = tool_manager
+        .execute_tool("print_presence_flag", json!({ "verbose": true, "target": "all" }), &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+    assert_eq!(lines, vec!["--verbose", "all"]);
+
+    let output = tool_manager
+        .execute_tool("print_presence_flag", json!({ "verbose": false, "target": "all" }), &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+    assert_eq!(lines, vec!["all"]);
+}
+
+#[tokio::test]
+async fn test_large_numbers_keep_exact_digits() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let cases = [
+        (json!(18446744073709551615u64), "18446744073709551615"),
+        (json!(-9223372036854775808i64), "-9223372036854775808"),
+        (json!(9007199254740993u64), "9007199254740993"),
+        (json!(1e21), "1000000000000000000000"),
+        (json!(0.000001), "0.000001"),
+    ];
+    for (count, expected) in cases {
+        let output = tool_manager
+            .execute_tool("print_count", json!({ "count": count }), &HashMap::new())
+            .await
+            .unwrap();
+        let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+        assert_eq!(lines, vec!["--count", expected]);
+    }
+}
+
+#[tokio::test]
+async fn test_array_arg_expands_to_separate_argv() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let args = json!({
+        "files": ["a.txt", "b.txt", "c d.txt"]
+    });
+
+    let output = tool_manager
+        .execute_tool("print_files", args.clone(), &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+    assert_eq!(lines, vec!["a.txt", "b.txt", "c d.txt"]);
+
+    // With a cli_flag every element gets its own flag
+    let output = tool_manager
+        .execute_tool("print_flagged_files", args, &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+    assert_eq!(lines, vec!["-f", "a.txt", "-f", "b.txt", "-f", "c d.txt"]);
+}
+
+#[tokio::test]
+async fn test_glob_expansion() {
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["main.rs", "lib.rs", "notes.txt"] {
+        tokio::fs::write(temp_dir.path().join(name), "").await.unwrap();
+    }
+
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let pattern = format!("{}/*.rs", temp_dir.path().display());
+    let args = json!({
+        "files": [pattern]
+    });
+
+    let output = tool_manager
+        .execute_tool("print_globbed_files", args, &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+
+    let expected = vec![
+        temp_dir.path().join("lib.rs").display().to_string(),
+        temp_dir.path().join("main.rs").display().to_string(),
+    ];
+    assert_eq!(lines, expected);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_glob_matches_checked_under_allowed_root() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().join("root");
+    tokio::fs::create_dir(&root).await.unwrap();
+    tokio::fs::write(root.join("notes.txt"), "").await.unwrap();
+    let config = temp_dir.path().join("glob_tools.yaml");
+    tokio::fs::write(
+        &config,
+        format!(
+            r#"
+tools:
+  - name: rooted_glob
+    description: Print files matched under a fixed root
+    command: printf
+    static_flags:
+      - "%s\n"
+    validation:
+      validate_paths: true
+      allowed_root: "{}"
+    args:
+      - name: files
+        description: Files or glob patterns
+        required: true
+        type: array
+        is_path: true
+        expand_globs: true
+"#,
+            root.display()
+        ),
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    tool_manager.load_from_file(&config).await.unwrap();
+    let run = |pattern: &str| {
+        let tool_manager = &tool_manager;
+        let args = json!({ "files": [pattern] });
+        async move { tool_manager.execute_tool("rooted_glob", args, &HashMap::new()).await }
+    };
+
+    // Matched under the root, not the server's directory, and relative to where it runs
+    let output = run("*").await.unwrap();
+    assert_eq!(output["output"], "notes.txt");
+
+    // A symlink out of the root is rejected whether it's named or matched
+    std::os::unix::fs::symlink("/etc/passwd", root.join("escape.txt")).unwrap();
+    let named = run("escape.txt").await.unwrap_err();
+    assert!(named.to_string().contains("escapes allowed root"), "{}", named);
+    let matched = run("*.txt").await.unwrap_err();
+    assert!(format!("{:#}", matched).contains("matched a rejected path 'escape.txt'"), "{:#}", matched);
+    assert!(format!("{:#}", matched).contains("escapes allowed root"), "{:#}", matched);
+}
+
+#[tokio::test]
+async fn test_default_numeric_arg_applied() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // Omitted argument falls back to the declared default
+    let output = tool_manager
+        .execute_tool("print_count", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+    assert_eq!(lines, vec!["--count", "42"]);
+
+    // An explicit value still wins
+    let output = tool_manager
+        .execute_tool("print_count", json!({ "count": 7 }), &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+    assert_eq!(lines, vec!["--count", "7"]);
+
+    // Required-with-default is advertised as optional
+    let tools = tool_manager.get_mcp_tools();
+    let tool = tools.iter().find(|t| t.name == "print_count").unwrap();
+    assert_eq!(tool.input_schema["properties"]["count"]["default"], 42);
+    assert!(!tool.input_schema["required"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("count")));
+}
+
+#[tokio::test]
+async fn test_keyvalue_arg_expansion() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let args = json!({
+        "settings": {
+            "LOG_LEVEL": "debug",
+            "RETRIES": 3,
+            "GREETING": "hello; rm -rf /"
+        }
+    });
+
+    let output = tool_manager
+        .execute_tool("print_settings", args, &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = output["output"].as_str().unwrap().lines().collect();
+
+    // Each pair is a single argv entry, so metacharacters stay literal
+    assert_eq!(
+        lines,
+        vec![
+            "--set",
+            "GREETING=hello; rm -rf /",
+            "--set",
+            "LOG_LEVEL=debug",
+            "--set",
+            "RETRIES=3"
+        ]
+    );
+
+    let tools = tool_manager.get_mcp_tools();
+    let tool = tools.iter().find(|t| t.name == "print_settings").unwrap();
+    assert_eq!(tool.input_schema["properties"]["settings"]["type"], "object");
+}
+
+#[tokio::test]
+async fn test_keyvalue_arg_rejects_bad_keys() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    for (key, expected) in [
+        ("A=B", "must not contain '='"),
+        ("--inject", "must not start with '-'"),
+        ("", "must not be empty"),
+    ] {
+        let args = json!({ "settings": { key: "value" } });
+        let result = tool_manager.execute_tool("print_settings", args, &HashMap::new()).await;
+        assert!(result.is_err(), "Key '{}' should be rejected", key);
+        assert!(result.unwrap_err().to_string().contains(expected));
+    }
+
+    // Nested values can't be flattened into KEY=VALUE
+    let args = json!({ "settings": { "NESTED": { "a": 1 } } });
+    let result = tool_manager.execute_tool("print_settings", args, &HashMap::new()).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_output_path_extraction() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/output_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // Without output_path the whole document comes back
+    let output = tool_manager
+        .execute_tool("nested_json", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(output["meta"]["count"], 2);
+
+    // With output_path only the selected value is returned
+    let output = tool_manager
+        .execute_tool("nested_json_extracted", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(output, json!({ "name": "second", "size": 2 }));
+
+    // A path that doesn't match is an error, not a silent null
+    let result = tool_manager
+        .execute_tool("nested_json_missing", json!({}), &HashMap::new())
+        .await;
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("did not match"));
+}
+
+#[tokio::test]
+async fn test_output_parsers() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/output_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let output = tool_manager
+        .execute_tool("status_lines", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(output, json!([" M src/main.rs", "?? notes.md"]));
+
+    // One object per match; a group that didn't take part is null
+    let output = tool_manager
+        .execute_tool("status_entries", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(
+        output,
+        json!([
+            { "status": " M", "path": "src/main.rs", "renamed_to": null },
+            { "status": "R ", "path": "old.txt", "renamed_to": "new.txt" }
+        ])
+    );
+
+    let output = tool_manager
+        .execute_tool("strict_json", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(output, json!([1, 2, 3]));
+
+    // "json" doesn't fall back to {output, status} like the default does
+    let result = tool_manager
+        .execute_tool("strict_json_invalid", json!({}), &HashMap::new())
+        .await;
+    let message = format!("{:#}", result.unwrap_err());
+    assert!(message.contains("Output of tool 'strict_json_invalid' is not valid JSON"), "{}", message);
+}
+
+#[tokio::test]
+async fn test_result_size_cap() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/output_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // Plain output is cut as text
+    let output = tool_manager
+        .execute_tool("long_output", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    let full: Vec<String> = (1..=1000).map(|n| n.to_string()).collect();
+    let full = full.join("\n");
+    assert_eq!(output["output"], full[..20]);
+    assert_eq!(output["truncated"], true);
+    assert_eq!(output["original_length"], full.len());
+    assert_eq!(output["status"], "success");
+
+    // Structured results are cut as their JSON text
+    let output = tool_manager
+        .execute_tool("long_lines", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(output["output"], "[\"1\",\"2\",\"3\",\"4\",\"5\"");
+    assert_eq!(output["truncated"], true);
+
+    // The server-wide cap applies to tools without their own; short results are untouched
+    // and extra content blocks survive
+    tool_manager.set_max_result_chars(30);
+    let output = tool_manager
+        .execute_tool("nested_json", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(output["truncated"], true);
+    let output = tool_manager
+        .execute_tool("image_result", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    assert!(output.get("truncated").is_none());
+    assert_eq!(output["_content"][0]["type"], "image");
+}
+
+#[tokio::test]
+async fn test_echo_call_envelope() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/output_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // Params are echoed after defaults are applied
+    let output = tool_manager
+        .execute_tool("echoed_call", json!({ "label": "x" }), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(output["tool"], "echoed_call");
+    assert_eq!(output["params"], json!({ "label": "x", "path": "." }));
+    let argv: Vec<&str> = output["result"]["output"].as_str().unwrap().lines().collect();
+    assert_eq!(argv, vec![".", "x"]);
+
+    // Off by default for other tools
+    let output = tool_manager
+        .execute_tool("nested_json", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    assert!(output.get("tool").is_none());
+
+    // The global switch wraps everything, and extra content stays at the top level
+    tool_manager.set_echo_calls(true);
+    let output = tool_manager
+        .execute_tool("image_result", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(output["tool"], "image_result");
+    assert_eq!(output["result"], json!({ "status": "rendered" }));
+    assert_eq!(output["_content"][0]["type"], "image");
+}
+
+#[tokio::test]
+async fn test_arg_constraints_enforced() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/constrained_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let result = tool_manager
+        .execute_tool("render", json!({"format": "svg", "scale": 2, "label": "chart"}), &HashMap::new())
+        .await
+        .unwrap();
+    let argv: Vec<&str> = result["output"].as_str().unwrap().lines().collect();
+    assert_eq!(argv, vec!["--format", "svg", "--scale", "2", "--label", "chart"]);
+
+    let rejected = [
+        (json!({"format": "gif"}), "/format"),
+        (json!({"scale": 0}), "/scale"),
+        (json!({"scale": 4.5}), "/scale"),
+        (json!({"label": "Chart-1"}), "/label"),
+    ];
+    for (args, location) in rejected {
+        let result = tool_manager.execute_tool("render", args.clone(), &HashMap::new()).await;
+        assert!(result.is_err(), "{} should be rejected", args);
+        assert!(result.unwrap_err().to_string().starts_with(location));
+    }
+}
+
+#[tokio::test]
+async fn test_arg_constraints_in_schema() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/constrained_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let tools = tool_manager.get_mcp_tools();
+    let properties = &tools[0].input_schema["properties"];
+    assert_eq!(properties["format"]["enum"], json!(["png", "svg", "pdf"]));
+    assert_eq!(properties["scale"]["minimum"], json!(1.0));
+    assert_eq!(properties["scale"]["maximum"], json!(4.0));
+    assert_eq!(properties["label"]["pattern"], "^[a-z]+$");
+}
+
+#[tokio::test]
+async fn test_diagram_output_dir() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/diagram_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // Without an allowed_root, output_dir must be under the server's directory
+    let temp_dir = TempDir::new_in(".").unwrap();
+    let output_dir = temp_dir.path().join("diagrams");
+    let root = temp_dir.path().canonicalize().unwrap().join("diagrams");
+
+    let cases = [
+        ("graphviz_diagram", "digraph { a -> b }", "dot"),
+        ("plantuml_diagram", "@startuml\na -> b\n@enduml", "puml"),
+    ];
+    for (tool, content, extension) in cases {
+        let args = json!({
+            "filename": "flow",
+            "content": content,
+            "output_dir": output_dir.to_str().unwrap()
+        });
+        let result = tool_manager.execute_tool(tool, args, &HashMap::new()).await;
+
+        // The source is written (into output_dir, created on demand) before rendering,
+        // so this holds even where the renderer isn't installed
+        let source = root.join(format!("flow.{}", extension));
+        assert_eq!(std::fs::read_to_string(&source).unwrap(), content);
+
+        match result {
+            Ok(output) => {
+                assert_eq!(output["source_file"], source.to_str().unwrap());
+                assert_eq!(output["output_file"], root.join("flow.svg").to_str().unwrap());
+                assert!(root.join("flow.svg").exists());
+            }
+            // A missing renderer is reported by name rather than as a bare io error
+            Err(e) => assert!(e.to_string().contains("binary not found on PATH"), "{}", e),
+        }
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_diagram_output_dir_stays_under_root() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/diagram_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let temp_dir = TempDir::new_in(".").unwrap();
+    let outside = TempDir::new().unwrap();
+    std::os::unix::fs::symlink(outside.path(), temp_dir.path().join("link")).unwrap();
+    let dir_name = temp_dir.path().file_name().unwrap().to_str().unwrap();
+
+    // Through a symlink, including directories that would be created beyond it,
+    // and straight outside the root
+    let linked = format!("{}/link", dir_name);
+    let created = format!("{}/link/new/deeper", dir_name);
+    for output_dir in [linked.as_str(), created.as_str(), outside.path().to_str().unwrap()] {
+        let args = json!({
+            "filename": "flow",
+            "content": "digraph { a -> b }",
+            "output_dir": output_dir
+        });
+        let err = tool_manager
+            .execute_tool("graphviz_diagram", args, &HashMap::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("escapes allowed root"), "{}: {}", output_dir, err);
+    }
+    assert_eq!(std::fs::read_dir(outside.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_diagram_format_limited_to_supported() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/diagram_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // The fixture declares no enum_values - the handler checks on its own
+    let temp_dir = TempDir::new_in(".").unwrap();
+    for (tool, format) in [("graphviz_diagram", "svg -o/tmp/x"), ("plantuml_diagram", "../x"), ("plantuml_diagram", "jpg")] {
+        let args = json!({
+            "filename": "flow",
+            "format": format,
+            "content": "digraph { a -> b }",
+            "output_dir": temp_dir.path().to_str().unwrap()
+        });
+        let err = tool_manager.execute_tool(tool, args, &HashMap::new()).await.unwrap_err();
+        assert!(err.to_string().contains("argument 'format' must be one of"), "{}", err);
+    }
+    assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_diagram_filename_rejects_separators() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/diagram_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let temp_dir = TempDir::new().unwrap();
+    for filename in ["sub/flow", "..", "a\\b"] {
+        let args = json!({
+            "filename": filename,
+            "content": "digraph { a -> b }",
+            "output_dir": temp_dir.path().to_str().unwrap()
+        });
+        let result = tool_manager
+            .execute_tool("graphviz_diagram", args, &HashMap::new())
+            .await;
+        assert!(result.is_err(), "Filename '{}' should be rejected", filename);
+        assert!(result.unwrap_err().to_string().contains("path separators"));
+    }
+    assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_missing_command_reported_by_name() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("missing.yaml");
+    tokio::fs::write(
+        &path,
+        r#"
+tools:
+  - name: missing_binary
+    description: Command that is not installed
+    command: definitely-not-a-real-binary
+"#,
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let result = tool_manager
+        .execute_tool("missing_binary", json!({}), &HashMap::new())
+        .await;
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Command 'definitely-not-a-real-binary' not found on PATH"
+    );
+}
+
+// Collects formatted log output so tests can assert on it
+#[derive(Clone, Default)]
+struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_unknown_args_logged_when_enabled() {
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/test_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // Off by default - the schema rejects the undeclared argument
+    let args = json!({"message": "hi", "verbose": true});
+    let err = tool_manager.execute_tool("echo_test", args.clone(), &HashMap::new()).await.unwrap_err();
+    assert!(matches!(err, ToolError::SchemaViolations(_)), "{:?}", err);
+    assert!(!String::from_utf8_lossy(&logs.0.lock().unwrap()).contains("Undeclared arguments"));
+
+    // When enabled it's logged and dropped, and the call runs without it
+    tool_manager.set_log_unknown_args(true);
+    let result = tool_manager.execute_tool("echo_test", args, &HashMap::new()).await.unwrap();
+    assert_eq!(result["output"], "hi");
+    let output = String::from_utf8_lossy(&logs.0.lock().unwrap()).to_string();
+    assert!(output.contains("Undeclared arguments in tools/call; dropped"), "{}", output);
+    assert!(output.contains("tool=\"echo_test\""), "{}", output);
+    assert!(output.contains("unknown_args=[\"verbose\"]"), "{}", output);
+}
+
+#[tokio::test]
+async fn test_describe_config_handler() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/describe_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let result = tool_manager
+        .execute_tool("describe_config", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        json!({
+            "sources": ["tests/fixtures/describe_tools.yaml"],
+            "mode": null,
+            "tool_count": 2,
+            "server_version": env!("CARGO_PKG_VERSION")
+        })
+    );
+
+    // Not available unless a tool is configured for it
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/test_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let result = tool_manager
+        .execute_tool("__describe_config", json!({}), &HashMap::new())
+        .await;
+    assert!(result.unwrap_err().to_string().contains("not found"));
+}
+
+#[tokio::test]
+async fn test_default_from_environment() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/argv_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // cargo sets CARGO_PKG_NAME for test runs - no racy set_var needed
+    let result = tool_manager
+        .execute_tool("print_env_default", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    let argv: Vec<&str> = result["output"].as_str().unwrap().lines().collect();
+    assert_eq!(argv, vec!["--project", env!("CARGO_PKG_NAME")]);
+
+    // The environment value is never advertised
+    let tools = tool_manager.get_mcp_tools();
+    let tool = tools.iter().find(|t| t.name == "print_env_default").unwrap();
+    assert!(tool.input_schema["properties"]["project"].get("default").is_none());
+    assert!(tool.input_schema["required"].as_array().unwrap().is_empty());
+
+    let result = tool_manager
+        .execute_tool("print_missing_env_default", json!({}), &HashMap::new())
+        .await;
+    let message = format!("{:#}", result.unwrap_err());
+    assert!(message.contains("Environment variable 'GAMECODE_TEST_NEVER_SET' is not set"), "{}", message);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_secrets_file_env() {
+    let temp_dir = TempDir::new().unwrap();
+    let secrets = temp_dir.path().join("secrets.env");
+    tokio::fs::write(
+        &secrets,
+        "# deployment secrets\nexport SECRET_KEY=\"s3cret value\"\nOTHER_SECRET=unused\n",
+    )
+    .await
+    .unwrap();
+    let config = temp_dir.path().join("tools.yaml");
+    tokio::fs::write(
+        &config,
+        r#"
+tools:
+  - name: show_env
+    description: Print the command's environment
+    command: env
+    env:
+      API_KEY: "key=${SECRET_KEY}"
+  - name: show_missing
+    description: References a secret that isn't defined
+    command: env
+    env:
+      API_KEY: "${GAMECODE_TEST_NEVER_SET}"
+"#,
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    tool_manager.load_secrets_file(&secrets).await.unwrap();
+    tool_manager.load_from_file(&config).await.unwrap();
+
+    let result = tool_manager
+        .execute_tool("show_env", json!({}), &HashMap::new())
+        .await
+        .unwrap();
+    let env: Vec<&str> = result["output"].as_str().unwrap().lines().collect();
+    assert!(env.contains(&"API_KEY=key=s3cret value"), "{:?}", env);
+    // Secrets only reach the command through an explicit reference
+    assert!(!env.iter().any(|line| line.starts_with("SECRET_KEY=")), "{:?}", env);
+    assert!(!env.iter().any(|line| line.contains("unused")), "{:?}", env);
+    assert!(std::env::var("SECRET_KEY").is_err());
+
+    let result = tool_manager
+        .execute_tool("show_missing", json!({}), &HashMap::new())
+        .await;
+    let message = format!("{:#}", result.unwrap_err());
+    assert!(message.contains("Cannot resolve env 'API_KEY' for tool 'show_missing'"), "{}", message);
+
+    // Malformed lines are reported by number without echoing their content
+    tokio::fs::write(&secrets, "GOOD=1\nhunter2\n").await.unwrap();
+    let message = format!("{:#}", tool_manager.load_secrets_file(&secrets).await.unwrap_err());
+    assert!(message.contains("Line 2 is not KEY=VALUE"), "{}", message);
+    assert!(!message.contains("hunter2"), "{}", message);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_shell_mode() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = temp_dir.path().join("tools.yaml");
+    tokio::fs::write(
+        &config,
+        r#"
+tools:
+  - name: first_sorted
+    description: Sort words and keep the first two
+    command: "printf '%s\n' {words} | sort | head -n 2"
+    shell: true
+    args:
+      - name: words
+        description: Words to sort
+        required: true
+        type: array
+  - name: shout
+    description: Upper-case text, which may contain anything
+    command: "printf '%s\n' {text} | tr a-z A-Z"
+    shell: true
+    allow_shell_metacharacters: true
+    args:
+      - name: text
+        description: Text
+        required: true
+        type: string
+"#,
+    )
+    .await
+    .unwrap();
+
+    // Off unless the server allows it
+    let mut tool_manager = ToolManager::new();
+    let message = format!("{:#}", tool_manager.load_from_file(&config).await.unwrap_err());
+    assert!(message.contains("--allow-shell"), "{}", message);
+
+    let mut tool_manager = ToolManager::new();
+    tool_manager.set_allow_shell(true);
+    tool_manager.load_from_file(&config).await.unwrap();
+
+    let result = tool_manager
+        .execute_tool("first_sorted", json!({"words": ["pear", "apple", "fig"]}), &HashMap::new())
+        .await
+        .unwrap();
+    let lines: Vec<&str> = result["output"].as_str().unwrap().lines().collect();
+    assert_eq!(lines, vec!["apple", "fig"]);
+
+    // Metacharacters in values are errors, not warnings
+    let result = tool_manager
+        .execute_tool("first_sorted", json!({"words": ["a; touch pwned"]}), &HashMap::new())
+        .await;
+    assert!(matches!(result, Err(ToolError::InvalidArguments(_))), "{:?}", result);
+
+    // When allowed, quoting keeps them literal
+    let result = tool_manager
+        .execute_tool("shout", json!({"text": "it's $(whoami) | {text}"}), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(result["output"].as_str().unwrap().trim_end(), "IT'S $(WHOAMI) | {TEXT}");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_timeout_stops_command_with_sigterm() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/timeout_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let started = std::time::Instant::now();
+    let err = tool_manager
+        .execute_tool("sleepy", json!({}), &HashMap::new())
+        .await
+        .unwrap_err();
+
+    // sleep exits on SIGTERM, so there's no need to escalate
+    match err {
+        ToolError::Timeout { message, signal } => {
+            assert_eq!(message, "Tool 'sleepy' timed out after 200ms");
+            assert_eq!(signal, "SIGTERM");
+        }
+        other => panic!("Expected a timeout, got {:?}", other),
+    }
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+}
+
+// Gone, or a zombie waiting for init - either way no longer running
+#[cfg(target_os = "linux")]
+fn process_running(pid: &str) -> bool {
+    std::fs::read_to_string(format!("/proc/{}/stat", pid))
+        .is_ok_and(|stat| stat.rsplit(')').next().is_some_and(|rest| !rest.trim_start().starts_with('Z')))
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_timeout_stops_the_whole_process_group() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/timeout_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let temp_dir = TempDir::new().unwrap();
+    let pid_file = temp_dir.path().join("sleep.pid");
+    let err = tool_manager
+        .execute_tool("pipeline", json!({ "pid_file": pid_file.to_str().unwrap() }), &HashMap::new())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ToolError::Timeout { .. }), "{:?}", err);
+
+    // The shell's own children were signalled too, not left behind as orphans
+    let pid = std::fs::read_to_string(&pid_file).unwrap();
+    let pid = pid.trim();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+    while process_running(pid) && std::time::Instant::now() < deadline {
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(!process_running(pid), "background sleep {} still running", pid);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_timeout_covers_output_held_open_after_exit() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/timeout_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // The shell exits at once, but its background sleep keeps stdout open for 6s
+    let started = std::time::Instant::now();
+    let err = tool_manager
+        .execute_tool("lingering", json!({}), &HashMap::new())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ToolError::Timeout { .. }), "{:?}", err);
+    assert!(started.elapsed() < std::time::Duration::from_secs(3), "{:?}", started.elapsed());
+}
+
+#[tokio::test]
+async fn test_list_files_recursion_filter_and_sort() {
+    let temp_dir = TempDir::new_in(".").unwrap();
+    let root = temp_dir.path();
+    tokio::fs::create_dir_all(root.join("sub/deeper")).await.unwrap();
+    tokio::fs::write(root.join("a.txt"), "0123456789").await.unwrap();
+    tokio::fs::write(root.join("big.txt"), "x".repeat(100)).await.unwrap();
+    tokio::fs::write(root.join("sub/c.rs"), "").await.unwrap();
+    tokio::fs::write(root.join("sub/deeper/d.rs"), "").await.unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/list_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let list = |args: serde_json::Value| {
+        let tool_manager = &tool_manager;
+        async move {
+            let output = tool_manager.execute_tool("tree", args, &HashMap::new()).await.unwrap();
+            output["files"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|f| f["path"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+    let dir = root.file_name().unwrap().to_str().unwrap();
+
+    // One level by default, sorted by name
+    assert_eq!(list(json!({ "path": dir })).await, vec!["a.txt", "big.txt", "sub"]);
+
+    // Depth is bounded
+    assert_eq!(
+        list(json!({ "path": dir, "recursive": true, "max_depth": 1 })).await,
+        vec!["a.txt", "big.txt", "sub", "sub/c.rs", "sub/deeper"]
+    );
+    assert!(list(json!({ "path": dir, "recursive": true })).await.contains(&"sub/deeper/d.rs".to_string()));
+
+    // The filter applies to relative paths, but recursion still walks every directory
+    assert_eq!(
+        list(json!({ "path": dir, "recursive": true, "glob": "*.rs" })).await,
+        vec!["sub/c.rs", "sub/deeper/d.rs"]
+    );
+
+    // Largest first
+    assert_eq!(
+        list(json!({ "path": dir, "glob": "*.txt", "sort_by": "size" })).await,
+        vec!["big.txt", "a.txt"]
+    );
+
+    // Entries carry a modification time
+    let output = tool_manager
+        .execute_tool("tree", json!({ "path": dir }), &HashMap::new())
+        .await
+        .unwrap();
+    assert!(output["files"][0]["modified"].as_u64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn test_list_files_validates_base_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().join("root");
+    tokio::fs::create_dir_all(root.join("docs")).await.unwrap();
+    tokio::fs::write(root.join("docs/readme.md"), "").await.unwrap();
+    let outside = TempDir::new().unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(outside.path(), root.join("escape")).unwrap();
+
+    // No validate_paths or is_path - the handler checks the base on its own
+    let config = temp_dir.path().join("list_tools.yaml");
+    tokio::fs::write(
+        &config,
+        format!(
+            r#"
+tools:
+  - name: rooted_list
+    description: List under a fixed root
+    command: internal
+    internal_handler: list_files
+    validation:
+      allowed_root: "{}"
+    args:
+      - name: path
+        description: Directory to list
+        required: false
+        type: string
+        default: "."
+"#,
+            root.display()
+        ),
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    tool_manager.load_from_file(&config).await.unwrap();
+    let list = |path: &str| {
+        let tool_manager = &tool_manager;
+        let args = json!({ "path": path });
+        async move { tool_manager.execute_tool("rooted_list", args, &HashMap::new()).await }
+    };
+
+    let output = list("docs").await.unwrap();
+    assert_eq!(output["files"][0]["name"], "readme.md");
+    assert!(output.get("skipped").is_none());
+
+    assert!(list("../").await.unwrap_err().to_string().contains("Path traversal"));
+    let absolute = outside.path().to_str().unwrap().to_string();
+    assert!(list(&absolute).await.unwrap_err().to_string().contains("Absolute paths not allowed"));
+    #[cfg(unix)]
+    assert!(list("escape").await.unwrap_err().to_string().contains("escapes allowed root"));
+}
+
+#[tokio::test]
+async fn test_write_file_validates_its_target() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().join("root");
+    tokio::fs::create_dir(&root).await.unwrap();
+    let outside = TempDir::new().unwrap();
+
+    // No validate_paths or is_path - the handler checks the target on its own
+    let config = temp_dir.path().join("write_tools.yaml");
+    tokio::fs::write(
+        &config,
+        format!(
+            r#"
+tools:
+  - name: rooted_writer
+    description: Write under a fixed root
+    command: internal
+    internal_handler: write_file
+    validation:
+      allow_absolute_paths: true
+      allowed_root: "{}"
+    args:
+      - name: path
+        description: File to write
+        required: true
+        type: string
+      - name: content
+        description: Content to write
+        required: true
+        type: string
+"#,
+            root.display()
+        ),
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    tool_manager.load_from_file(&config).await.unwrap();
+    let write = |path: String| {
+        let tool_manager = &tool_manager;
+        let args = json!({ "path": path, "content": "written" });
+        async move { tool_manager.execute_tool("rooted_writer", args, &HashMap::new()).await }
+    };
+
+    // Relative paths land under the root
+    write("inside.txt".to_string()).await.unwrap();
+    assert_eq!(tokio::fs::read_to_string(root.join("inside.txt")).await.unwrap(), "written");
+
+    let target = outside.path().join("outside.txt");
+    let err = write(target.to_str().unwrap().to_string()).await.unwrap_err();
+    assert!(err.to_string().contains("escapes allowed root"), "{}", err);
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(outside.path(), root.join("link")).unwrap();
+        let err = write("link/outside.txt".to_string()).await.unwrap_err();
+        assert!(err.to_string().contains("escapes allowed root"), "{}", err);
+    }
+    assert!(!target.exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_list_files_reports_unreadable_directories() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new_in(".").unwrap();
+    let locked = temp_dir.path().join("locked");
+    tokio::fs::create_dir(&locked).await.unwrap();
+    tokio::fs::write(temp_dir.path().join("open.txt"), "").await.unwrap();
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+    // Permissions don't stop root, so there's nothing to report there
+    if std::fs::read_dir(&locked).is_ok() {
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        return;
+    }
+
+    let mut tool_manager = ToolManager::new();
+    tool_manager.load_from_file(&PathBuf::from("tests/fixtures/list_tools.yaml")).await.unwrap();
+    let dir = temp_dir.path().file_name().unwrap().to_str().unwrap();
+    let output = tool_manager
+        .execute_tool("tree", json!({ "path": dir, "recursive": true }), &HashMap::new())
+        .await;
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    // The rest of the listing survives, and the unreadable directory says why
+    let output = output.unwrap();
+    let names: Vec<&str> = output["files"].as_array().unwrap().iter().map(|f| f["name"].as_str().unwrap()).collect();
+    assert_eq!(names, vec!["locked", "open.txt"]);
+    assert_eq!(output["skipped"][0]["path"], "locked");
+    assert!(output["skipped"][0]["error"].as_str().unwrap().contains("ermission denied"));
+}
+
+#[tokio::test]
+async fn test_git_handler_whitelist() {
+    let temp_dir = TempDir::new().unwrap();
+    let repo = temp_dir.path().join("repo");
+    tokio::fs::create_dir(&repo).await.unwrap();
+    tokio::fs::write(repo.join("notes.txt"), "hello\n").await.unwrap();
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(&repo)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    };
+    git(&["init", "-q"]);
+    git(&["add", "notes.txt"]);
+    git(&["commit", "-q", "-m", "Add notes"]);
+
+    // The repository is pinned through allowed_root, not chosen by the caller
+    let config = temp_dir.path().join("git_tools.yaml");
+    tokio::fs::write(
+        &config,
+        format!(
+            r#"
+tools:
+  - name: git
+    description: Read-only git
+    command: internal
+    internal_handler: git
+    validation:
+      allowed_root: "{}"
+    args:
+      - name: subcommand
+        description: status, log, diff or show
+        required: true
+        type: string
+      - name: flags
+        description: Allowed flags for the subcommand
+        required: false
+        type: array
+      - name: revision
+        description: Revision to show or compare
+        required: false
+        type: string
+      - name: max_count
+        description: Limit for log
+        required: false
+        type: number
+"#,
+            repo.display()
+        ),
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    tool_manager.load_from_file(&config).await.unwrap();
+
+    let output = tool_manager
+        .execute_tool(
+            "git",
+            json!({ "subcommand": "log", "flags": ["--oneline"], "max_count": 1, "revision": "HEAD" }),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let lines = output["lines"].as_array().unwrap();
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].as_str().unwrap().ends_with("Add notes"));
+
+    // Subcommands and flags outside the whitelist never reach git
+    let err = tool_manager
+        .execute_tool("git", json!({ "subcommand": "push" }), &HashMap::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("git subcommand 'push' is not allowed"));
+
+    let err = tool_manager
+        .execute_tool("git", json!({ "subcommand": "diff", "flags": ["--output=/tmp/x"] }), &HashMap::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("does not allow flag '--output=/tmp/x'"));
+
+    let err = tool_manager
+        .execute_tool("git", json!({ "subcommand": "show", "revision": "--help" }), &HashMap::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("must not start with '-'"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_git_handler_ignores_repo_filter_drivers() {
+    let temp_dir = TempDir::new().unwrap();
+    let repo = temp_dir.path().join("repo");
+    tokio::fs::create_dir(&repo).await.unwrap();
+    let marker = temp_dir.path().join("filter-ran");
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(&repo)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    };
+    git(&["init", "-q"]);
+    tokio::fs::write(repo.join("notes.txt"), "hello\n").await.unwrap();
+    git(&["add", "notes.txt"]);
+    git(&["commit", "-q", "-m", "Add notes"]);
+
+    // Config anyone able to write into the repo could plant - e.g. through write_file
+    tokio::fs::write(repo.join(".gitattributes"), "* filter=planted\n").await.unwrap();
+    let command = format!("sh -c 'touch {}; cat'", marker.display());
+    git(&["config", "filter.planted.clean", &command]);
+    git(&["config", "filter.planted.smudge", &command]);
+    git(&["config", "filter.planted.required", "true"]);
+    tokio::fs::write(repo.join("notes.txt"), "hello again\n").await.unwrap();
+
+    let config = temp_dir.path().join("git_tools.yaml");
+    tokio::fs::write(
+        &config,
+        format!(
+            r#"
+tools:
+  - name: git
+    description: Read-only git
+    command: internal
+    internal_handler: git
+    validation:
+      allowed_root: "{}"
+    args:
+      - name: subcommand
+        description: status, log, diff or show
+        required: true
+        type: string
+"#,
+            repo.display()
+        ),
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    tool_manager.load_from_file(&config).await.unwrap();
+    for subcommand in ["status", "diff"] {
+        let output = tool_manager
+            .execute_tool("git", json!({ "subcommand": subcommand }), &HashMap::new())
+            .await
+            .unwrap();
+        assert!(!output["lines"].as_array().unwrap().is_empty(), "{}", output);
+    }
+    assert!(!marker.exists(), "a repo filter driver ran");
+}
+
@@ -1,5 +1,8 @@
 use gamecode_mcp2::tools::ToolManager;
+use serde_json::json;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use tempfile::TempDir;
 
 #[tokio::test]
 async fn test_load_simple_tools() {
@@ -40,6 +43,60 @@ async fn test_load_tools_with_include() {
     assert!(tool_names.contains(&"additional_tool".to_string()));
 }
 
+#[tokio::test]
+async fn test_include_budget() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut includes = Vec::new();
+    for i in 0..5 {
+        let name = format!("part{}.yaml", i);
+        tokio::fs::write(temp_dir.path().join(&name), "tools: []\n").await.unwrap();
+        includes.push(format!("  - {}", name));
+    }
+    let main = temp_dir.path().join("main.yaml");
+    tokio::fs::write(&main, format!("include:\n{}\ntools: []\n", includes.join("\n")))
+        .await
+        .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    tool_manager.set_max_total_includes(3);
+    let err = tool_manager.load_from_file(&main).await.unwrap_err();
+    assert!(err.to_string().contains("Too many included files (limit 3)"));
+
+    // A file that includes itself runs into the default budget instead of recursing forever
+    let cycle = temp_dir.path().join("cycle.yaml");
+    tokio::fs::write(&cycle, "include:\n  - cycle.yaml\ntools: []\n").await.unwrap();
+    let mut tool_manager = ToolManager::new();
+    let err = tool_manager.load_from_file(&cycle).await.unwrap_err();
+    assert!(err.to_string().contains("Too many included files"));
+}
+
+#[tokio::test]
+async fn test_load_json_tools() {
+    let mut yaml_manager = ToolManager::new();
+    yaml_manager
+        .load_from_file(&PathBuf::from("tests/fixtures/test_tools.yaml"))
+        .await
+        .unwrap();
+
+    let mut json_manager = ToolManager::new();
+    let result = json_manager
+        .load_from_file(&PathBuf::from("tests/fixtures/test_tools.json"))
+        .await;
+    assert!(result.is_ok(), "Failed to load JSON tools: {:?}", result);
+
+    // The JSON fixture mirrors the YAML one, so both should expose the same tools
+    let mut yaml_names: Vec<String> = yaml_manager.get_mcp_tools().into_iter().map(|t| t.name).collect();
+    let mut json_names: Vec<String> = json_manager.get_mcp_tools().into_iter().map(|t| t.name).collect();
+    yaml_names.sort();
+    json_names.sort();
+    assert_eq!(yaml_names, json_names);
+
+    let tools = json_manager.get_mcp_tools();
+    let echo_tool = tools.iter().find(|t| t.name == "echo_test").unwrap();
+    assert_eq!(echo_tool.input_schema["properties"]["message"]["type"], "string");
+    assert_eq!(echo_tool.input_schema["required"][0], "message");
+}
+
 #[tokio::test]
 async fn test_tool_schema_generation() {
     let mut tool_manager = ToolManager::new();
@@ -84,3 +141,416 @@ async fn test_invalid_yaml_structure() {
         }
     }
 }
+
+#[tokio::test]
+async fn test_unknown_internal_handler_rejected() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/unknown_handler.yaml");
+
+    let result = tool_manager.load_from_file(&path).await;
+    assert!(result.is_err(), "Unknown handler should fail the load");
+
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("unknown internal handler 'multipy'"));
+    assert!(err.contains("multiply"), "Error should list valid handlers: {}", err);
+}
+
+#[tokio::test]
+async fn test_command_and_internal_handler_are_exclusive() {
+    let temp_dir = TempDir::new().unwrap();
+    let both = temp_dir.path().join("both.yaml");
+    tokio::fs::write(
+        &both,
+        r#"
+tools:
+  - name: confused_tool
+    description: Sets a command and a handler
+    command: cat
+    internal_handler: write_file
+"#,
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    let result = tool_manager.load_from_file(&both).await;
+    assert!(result.is_err(), "Ambiguous tool should fail the load");
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("sets both command 'cat' and internal_handler 'write_file'"));
+
+    let neither = temp_dir.path().join("neither.yaml");
+    tokio::fs::write(
+        &neither,
+        r#"
+tools:
+  - name: empty_tool
+    description: Has nothing to run
+    command: internal
+"#,
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    let result = tool_manager.load_from_file(&neither).await;
+    assert!(result.is_err(), "Tool without a mechanism should fail the load");
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("neither a command nor an internal_handler"));
+}
+
+#[tokio::test]
+async fn test_empty_config_warning() {
+    let mut tool_manager = ToolManager::new();
+    assert!(tool_manager.empty_config_warning().is_none(), "Nothing loaded yet");
+
+    let path = PathBuf::from("tests/fixtures/empty_tools.yaml");
+    let result = tool_manager.load_from_file(&path).await;
+    assert!(result.is_ok(), "Empty config should still load: {:?}", result);
+
+    let warning = tool_manager.empty_config_warning().unwrap();
+    assert_eq!(
+        warning,
+        "Loaded config from tests/fixtures/empty_tools.yaml but it defines no tools"
+    );
+
+    // A config with tools has nothing to warn about
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/test_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    assert!(tool_manager.empty_config_warning().is_none());
+}
+
+#[tokio::test]
+async fn test_tools_filtered_by_tag() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/tagged_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // No filter returns everything, tagged or not
+    assert_eq!(tool_manager.get_mcp_tools().len(), 4);
+    assert_eq!(tool_manager.get_mcp_tools_filtered(&[]).len(), 4);
+
+    let mut git: Vec<String> = tool_manager
+        .get_mcp_tools_filtered(&["git".to_string()])
+        .into_iter()
+        .map(|t| t.name)
+        .collect();
+    git.sort();
+    assert_eq!(git, vec!["git_log", "git_status"]);
+
+    // Any matching tag is enough
+    let mut mixed: Vec<String> = tool_manager
+        .get_mcp_tools_filtered(&["read-only".to_string(), "nonexistent".to_string()])
+        .into_iter()
+        .map(|t| t.name)
+        .collect();
+    mixed.sort();
+    assert_eq!(mixed, vec!["git_status", "list_dir"]);
+
+    // Tags are surfaced in the tool metadata
+    let tools = tool_manager.get_mcp_tools();
+    let status = tools.iter().find(|t| t.name == "git_status").unwrap();
+    assert_eq!(status.meta.as_ref().unwrap()["tags"][0], "git");
+    let echo = tools.iter().find(|t| t.name == "untagged_echo").unwrap();
+    assert!(echo.meta.is_none());
+}
+
+#[tokio::test]
+async fn test_tools_listed_by_priority() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/tagged_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // Highest priority first, then by name; the default priority is 0
+    let names: Vec<String> = tool_manager.get_mcp_tools().into_iter().map(|t| t.name).collect();
+    assert_eq!(names, vec!["list_dir", "git_status", "untagged_echo", "git_log"]);
+
+    // Filtering keeps the same order
+    let names: Vec<String> = tool_manager
+        .get_mcp_tools_filtered(&["read-only".to_string()])
+        .into_iter()
+        .map(|t| t.name)
+        .collect();
+    assert_eq!(names, vec!["list_dir", "git_status"]);
+}
+
+#[tokio::test]
+async fn test_tools_extend_templates() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/templated_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let status = tool_manager
+        .explain_tool("git_status", json!({}), &HashMap::new())
+        .unwrap();
+    assert_eq!(status["command"], "git");
+    assert_eq!(status["argv"], json!(["status", "--short"]));
+
+    // Template args come first, the tool's own args are appended
+    let log = tool_manager
+        .explain_tool("git_log", json!({"path": "src", "count": 5}), &HashMap::new())
+        .unwrap();
+    assert_eq!(log["command"], "git");
+    assert_eq!(log["argv"], json!(["log", "--oneline", "src", "-n", "5"]));
+
+    // Tool fields override the template
+    let tools = tool_manager.get_mcp_tools();
+    let log_tool = tools.iter().find(|t| t.name == "git_log").unwrap();
+    assert_eq!(log_tool.meta.as_ref().unwrap()["tags"], json!(["git", "history"]));
+}
+
+#[tokio::test]
+async fn test_unknown_template_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("orphan.yaml");
+    tokio::fs::write(
+        &path,
+        r#"
+tools:
+  - name: orphan_tool
+    extends: missing
+    description: Extends a template that does not exist
+    command: echo
+"#,
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    let err = tool_manager.load_from_file(&path).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("Unknown template 'missing'"));
+}
+
+#[tokio::test]
+async fn test_default_must_satisfy_constraints() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("bad_default.yaml");
+    tokio::fs::write(
+        &path,
+        r#"
+tools:
+  - name: bad_default_tool
+    description: Default outside its own enum
+    command: echo
+    args:
+      - name: format
+        description: Output format
+        required: false
+        type: string
+        default: "gif"
+        enum_values: ["png", "svg"]
+"#,
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    let err = tool_manager.load_from_file(&path).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("violates its constraints"));
+}
+
+#[tokio::test]
+async fn test_invalid_positions_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("bad_positions.yaml");
+    tokio::fs::write(
+        &path,
+        r#"
+tools:
+  - name: shared_position
+    description: Two args claim the same slot
+    command: cp
+    args:
+      - name: src
+        description: Source
+        required: true
+        type: string
+        position: 0
+      - name: dst
+        description: Destination
+        required: true
+        type: string
+        position: 0
+"#,
+    )
+    .await
+    .unwrap();
+
+    let mut tool_manager = ToolManager::new();
+    let err = tool_manager.load_from_file(&path).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("share position 0"));
+}
+
+#[tokio::test]
+async fn test_invalid_output_parsers_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("bad_parser.yaml");
+
+    for (parser, expected) in [
+        ("output_parser: csv", "unknown output_parser 'csv'"),
+        ("output_parser: regex", "without an output_pattern"),
+        ("output_parser: regex\n    output_pattern: '^(.+)$'", "has no named captures"),
+        ("output_parser: regex\n    output_pattern: '(?P<x>'", "Invalid output_pattern"),
+        ("output_pattern: '^(?P<x>.+)$'", "needs output_parser 'regex'"),
+    ] {
+        let config = format!(
+            "tools:\n  - name: parsed\n    description: Parsed output\n    command: ls\n    {}\n",
+            parser
+        );
+        tokio::fs::write(&path, config).await.unwrap();
+
+        let mut tool_manager = ToolManager::new();
+        let err = tool_manager.load_from_file(&path).await.unwrap_err();
+        assert!(format!("{:#}", err).contains(expected), "{}: {:#}", parser, err);
+    }
+}
+
+#[tokio::test]
+async fn test_load_multiple_files_in_order() {
+    let mut tool_manager = ToolManager::new();
+    tool_manager
+        .load_from_files(&[
+            "tests/fixtures/test_tools.yaml".to_string(),
+            "tests/fixtures/override_tools.yaml".to_string(),
+        ])
+        .await
+        .unwrap();
+
+    // 4 from the first file, echo_test replaced, extra_tool added
+    let mut names: Vec<String> = tool_manager.get_mcp_tools().into_iter().map(|t| t.name).collect();
+    names.sort();
+    assert_eq!(names, vec!["echo_test", "extra_tool", "file_writer", "list_dir", "math_add"]);
+
+    let tools = tool_manager.get_mcp_tools();
+    let echo = tools.iter().find(|t| t.name == "echo_test").unwrap();
+    assert_eq!(echo.description, "Overridden echo with a fixed prefix");
+
+    let result = tool_manager
+        .execute_tool("echo_test", json!({"message": "hi"}), &HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(result["output"], "override: hi");
+}
+
+#[tokio::test]
+async fn test_duplicate_tool_names() {
+    let path = PathBuf::from("tests/fixtures/duplicate_tools.yaml");
+
+    // Default: load succeeds, later definition wins, warning names both files
+    let mut tool_manager = ToolManager::new();
+    tool_manager.load_from_file(&path).await.unwrap();
+    let tools = tool_manager.get_mcp_tools();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0].description, "Second definition");
+    assert_eq!(
+        tool_manager.load_warnings(),
+        ["Duplicate tool 'greet': defined in tests/fixtures/duplicate_tools.yaml and again in tests/fixtures/duplicate_tools.yaml; the later definition wins"]
+    );
+
+    // Strict: the duplicate fails the load
+    let mut tool_manager = ToolManager::new();
+    tool_manager.set_strict(true);
+    let err = tool_manager.load_from_file(&path).await.unwrap_err();
+    assert!(err.to_string().contains("Duplicate tool 'greet'"));
+
+    // Overrides across separate files are intentional, not duplicates
+    let mut tool_manager = ToolManager::new();
+    tool_manager.set_strict(true);
+    tool_manager
+        .load_from_files(&[
+            "tests/fixtures/test_tools.yaml".to_string(),
+            "tests/fixtures/override_tools.yaml".to_string(),
+        ])
+        .await
+        .unwrap();
+    assert!(tool_manager.load_warnings().is_empty());
+}
+
+#[tokio::test]
+async fn test_config_schema_reports_every_error() {
+    let content = r#"
+tools:
+  - name: good_tool
+    description: Nothing wrong here
+    command: echo
+
+  - name: typo_tool
+    description: Misspelled field and a bad arg type
+    command: echo
+    timeout: 5
+    args:
+      - name: count
+        description: How many
+        required: true
+        type: integer
+
+  - name: no_description
+    command: echo
+    internal_handler: multipy
+"#;
+
+    let report = ToolManager::validate_config_schema(content);
+    assert_eq!(report.errors.len(), 3, "{:#?}", report.errors);
+
+    let find = |needle: &str| report.errors.iter().find(|e| e.contains(needle)).unwrap();
+    assert!(find("/tools/1/args/0/type").contains("line 7"));
+    assert!(find("\"description\" is a required property").contains("(tool 'no_description', line 17)"));
+    assert!(find("/tools/2/internal_handler").contains("multipy"));
+
+    // The loader ignores unknown keys, so they're warnings rather than errors
+    assert_eq!(report.warnings.len(), 1, "{:#?}", report.warnings);
+    assert!(report.warnings[0].contains("'timeout' was unexpected"));
+    assert!(report.warnings[0].ends_with("(tool 'typo_tool', line 7)"));
+
+    // Shipped fixtures pass
+    let valid = tokio::fs::read_to_string("tests/fixtures/test_tools.yaml").await.unwrap();
+    let report = ToolManager::validate_config_schema(&valid);
+    assert!(report.errors.is_empty() && report.warnings.is_empty(), "{:#?}", report);
+}
+
+
+#[test]
+fn test_examples_pass_check() {
+    fn collect(dir: &std::path::Path, found: &mut Vec<PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                collect(&path, found);
+            } else if matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml" | "json")) {
+                found.push(path);
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    collect(std::path::Path::new("../examples"), &mut files);
+    files.sort();
+
+    let mut checked = 0;
+    for file in &files {
+        // Skip neighbours that aren't tool configs, like the Slack app manifest
+        let content = std::fs::read_to_string(file).unwrap();
+        let Ok(value) = serde_yaml::from_str::<serde_yaml::Value>(&content) else {
+            continue;
+        };
+        if value.get("tools").is_none() {
+            continue;
+        }
+
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_gamecode-mcp2"))
+            .arg("--check")
+            .arg("--tools-file")
+            .arg(file)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{}:\n{}", file.display(), stdout);
+        assert!(!stdout.contains("Warning"), "{}:\n{}", file.display(), stdout);
+        checked += 1;
+    }
+    assert!(checked >= 10, "only {} example configs found", checked);
+}
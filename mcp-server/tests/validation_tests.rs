@@ -54,8 +54,8 @@ async fn test_validation_allows_safe_paths() {
 
     let result = tool_manager.execute_tool("safe_file_reader", args, &HashMap::new()).await;
     // This might fail if README.md doesn't exist, but shouldn't fail validation
-    if result.is_err() {
-        let err = result.unwrap_err().to_string();
+    if let Err(err) = result {
+        let err = err.to_string();
         assert!(!err.contains("Path traversal"));
         assert!(!err.contains("Absolute paths"));
     }
@@ -98,3 +98,155 @@ async fn test_null_byte_rejection() {
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("null byte"));
 }
+
+#[tokio::test]
+async fn test_read_file_path_traversal_rejection() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/read_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // Internal handlers honor the tool's path validation too
+    let args = json!({
+        "path": "../../../etc/passwd"
+    });
+
+    let result = tool_manager.execute_tool("file_reader", args, &HashMap::new()).await;
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Path traversal detected"));
+}
+
+#[tokio::test]
+async fn test_read_file_validates_without_yaml_config() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/read_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+    let read = |path: &str| {
+        let tool_manager = &tool_manager;
+        let args = json!({ "path": path });
+        async move { tool_manager.execute_tool("unvalidated_reader", args, &HashMap::new()).await }
+    };
+
+    // Neither validate_paths nor is_path is set, but the handler still checks
+    let err = read("../../../etc/passwd").await.unwrap_err();
+    assert!(err.to_string().contains("Path traversal detected"));
+    let err = read("/etc/passwd").await.unwrap_err();
+    assert!(err.to_string().contains("Absolute paths not allowed"));
+
+    let output = read("Cargo.toml").await.unwrap();
+    assert!(output["content"].as_str().unwrap().contains("gamecode-mcp2"));
+}
+
+#[tokio::test]
+async fn test_default_path_is_validated() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/read_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // The default path goes through the same checks as an explicit one
+    let result = tool_manager.execute_tool("defaulted_reader", json!({}), &HashMap::new()).await;
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Path traversal detected"));
+}
+
+#[tokio::test]
+async fn test_safe_read_file_rejects_traversal() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/read_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let args = json!({ "path": "../../../etc/passwd" });
+    let result = tool_manager.execute_tool("safe_reader", args, &HashMap::new()).await;
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Path traversal detected"));
+}
+
+#[tokio::test]
+async fn test_safe_read_file_rejects_absolute() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/read_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let args = json!({ "path": "/etc/passwd" });
+    let result = tool_manager.execute_tool("safe_reader", args, &HashMap::new()).await;
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Absolute paths not allowed"));
+}
+
+#[tokio::test]
+async fn test_safe_read_file_allows_safe_paths() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/read_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    let args = json!({ "path": "Cargo.toml" });
+    let result = tool_manager.execute_tool("safe_reader", args, &HashMap::new()).await;
+    assert!(result.is_ok(), "Safe path should be readable: {:?}", result);
+    assert!(result.unwrap()["content"]
+        .as_str()
+        .unwrap()
+        .contains("gamecode-mcp2"));
+}
+
+#[tokio::test]
+async fn test_safe_read_file_honors_root_and_size_cap() {
+    let mut tool_manager = ToolManager::new();
+    let path = PathBuf::from("tests/fixtures/read_tools.yaml");
+    tool_manager.load_from_file(&path).await.unwrap();
+
+    // Outside allowed_root, even without traversal
+    let outside = std::fs::canonicalize("Cargo.toml").unwrap();
+    let args = json!({ "path": outside.to_str().unwrap() });
+    let result = tool_manager.execute_tool("rooted_safe_reader", args, &HashMap::new()).await;
+    assert!(result.unwrap_err().to_string().contains("escapes allowed root"));
+
+    // Relative paths are under the root - this is tests/Cargo.toml, which doesn't exist
+    let args = json!({ "path": "Cargo.toml" });
+    let result = tool_manager.execute_tool("rooted_safe_reader", args, &HashMap::new()).await;
+    assert!(result.unwrap_err().to_string().contains("No such file"));
+
+    // Inside the root, but larger than max_read_bytes
+    let args = json!({ "path": "fixtures/read_tools.yaml" });
+    let result = tool_manager.execute_tool("rooted_safe_reader", args, &HashMap::new()).await;
+    assert!(result.unwrap_err().to_string().contains("exceeds limit"));
+}
+
+#[test]
+fn test_resolved_paths_are_relative_to_root() {
+    use gamecode_mcp2::validation::validate_path_resolved;
+
+    // The test's working directory is the crate, which the root is not inside
+    let root = tempfile::TempDir::new().unwrap();
+    std::fs::write(root.path().join("notes.txt"), "inside").unwrap();
+    let canonical_root = root.path().canonicalize().unwrap();
+
+    let resolved = validate_path_resolved("notes.txt", root.path()).unwrap();
+    assert_eq!(resolved, canonical_root.join("notes.txt"));
+
+    // Exists in the working directory, but resolves to the root's (not yet written) copy
+    assert!(std::path::Path::new("Cargo.toml").exists());
+    let resolved = validate_path_resolved("Cargo.toml", root.path()).unwrap();
+    assert_eq!(resolved, canonical_root.join("Cargo.toml"));
+
+    // A relative path through a symlink is still followed to its target
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(std::fs::canonicalize("src").unwrap(), root.path().join("link")).unwrap();
+        let err = validate_path_resolved("link/lib.rs", root.path()).unwrap_err();
+        assert!(err.to_string().contains("escapes allowed root"));
+    }
+
+    // Parents that don't exist under the root aren't looked up in the working directory
+    assert!(validate_path_resolved("src/new.rs", root.path()).is_err());
+}